@@ -0,0 +1,155 @@
+use std::fmt::{Display, Formatter};
+use core::fmt;
+
+/// Sorts the adapters and bookends them with the charging outlet (0J) and the device, which is
+/// rated `device_offset` jolts above the highest adapter.
+pub fn build_chain(adapters: &[i32], device_offset: i32) -> Vec<i32> {
+    let mut chain = adapters.to_vec();
+    chain.sort_unstable();
+
+    let device_joltage = chain.last().copied().unwrap_or(0) + device_offset;
+    chain.insert(0, 0);
+    chain.push(device_joltage);
+
+    chain
+}
+
+/// Counts the distinct ways the adapters can be arranged to connect the charging outlet (0J) to
+/// the device. Any two consecutive links in an arrangement may differ by at most `max_jump`
+/// jolts, and the device is rated `device_offset` jolts above the highest adapter.
+///
+/// The adapters don't need to be sorted. The count grows exponentially with the number of
+/// adapters, hence the u128.
+pub fn count_arrangements(adapters: &[i32], max_jump: i32, device_offset: i32) -> u128 {
+    let chain = build_chain(adapters, device_offset);
+
+    // paths[i] is the number of ways to reach chain[i] from the outlet
+    let mut paths = vec![0u128; chain.len()];
+    paths[0] = 1;
+
+    for i in 1..chain.len() {
+        for j in (0..i).rev() {
+            if chain[i] - chain[j] > max_jump {
+                break;
+            }
+            paths[i] += paths[j];
+        }
+    }
+
+    paths[chain.len() - 1]
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ChainBreak {
+    /// Position of `to` in the chain, counting the outlet as position 0
+    pub position: usize,
+    pub from: i32,
+    pub to: i32,
+    pub max_jump: i32,
+}
+
+impl Display for ChainBreak {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Chain breaks at position {}: {}J -> {}J is a jump of {}J, more than the allowed {}J",
+            self.position, self.from, self.to, self.to - self.from, self.max_jump
+        )
+    }
+}
+
+/// Checks that using every adapter connects the outlet to the device, returning the first gap
+/// that's larger than `max_jump` otherwise.
+pub fn validate_chain(adapters: &[i32], max_jump: i32, device_offset: i32) -> Result<(), ChainBreak> {
+    let chain = build_chain(adapters, device_offset);
+
+    for (i, pair) in chain.windows(2).enumerate() {
+        if pair[1] - pair[0] > max_jump {
+            return Err(ChainBreak {
+                position: i + 1,
+                from: pair[0],
+                to: pair[1],
+                max_jump,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Lists the adapters that can be taken out of the full chain, one at a time, without leaving a
+/// gap larger than `max_jump`. Dropping two of them together isn't necessarily safe.
+pub fn removable_adapters(adapters: &[i32], max_jump: i32, device_offset: i32) -> Vec<i32> {
+    let chain = build_chain(adapters, device_offset);
+
+    chain.windows(3)
+        .filter(|triple| triple[2] - triple[0] <= max_jump)
+        .map(|triple| triple[1])
+        .collect()
+}
+
+struct Frame {
+    joltage: i32,
+    next_candidate: usize,
+    checked_device: bool,
+}
+
+/// Lazily walks every subset of adapters that still connects the outlet to the device, yielding
+/// each one as a sorted list of joltages. Subsets come out in lexicographic order.
+pub struct Arrangements {
+    adapters: Vec<i32>,
+    device_joltage: i32,
+    max_jump: i32,
+    stack: Vec<Frame>,
+}
+
+impl Arrangements {
+    pub fn new(adapters: &[i32], max_jump: i32, device_offset: i32) -> Arrangements {
+        let mut chain = build_chain(adapters, device_offset);
+        let device_joltage = chain.pop().unwrap();
+        chain.remove(0);
+
+        Arrangements {
+            adapters: chain,
+            device_joltage,
+            max_jump,
+            stack: vec![Frame { joltage: 0, next_candidate: 0, checked_device: false }],
+        }
+    }
+
+    fn current_subset(&self) -> Vec<i32> {
+        self.stack[1..].iter().map(|frame| frame.joltage).collect()
+    }
+}
+
+impl Iterator for Arrangements {
+    type Item = Vec<i32>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let max_jump = self.max_jump;
+            let top = self.stack.last_mut()?;
+
+            // A subset is smaller than any of its extensions, so try finishing the chain here
+            // before adding more adapters to it
+            if !top.checked_device {
+                top.checked_device = true;
+                if self.device_joltage - top.joltage <= max_jump {
+                    return Some(self.current_subset());
+                }
+            }
+
+            let candidate = top.next_candidate;
+            if candidate < self.adapters.len() && self.adapters[candidate] - top.joltage <= max_jump {
+                top.next_candidate += 1;
+                self.stack.push(Frame {
+                    joltage: self.adapters[candidate],
+                    next_candidate: candidate + 1,
+                    checked_device: false,
+                });
+            } else {
+                self.stack.pop();
+            }
+        }
+    }
+}
@@ -2,6 +2,12 @@ use std::path::Path;
 use std::fs::File;
 use std::io::{BufReader, BufRead};
 use std::collections::HashMap;
+use std::env;
+use crate::adapter_chain::{build_chain, count_arrangements, validate_chain, removable_adapters, Arrangements};
+
+mod adapter_chain;
+#[cfg(test)]
+mod tests;
 
 fn get_input(filename: &str) -> Vec<i32> {
     let path = Path::new(filename);
@@ -18,33 +24,43 @@ fn get_input(filename: &str) -> Vec<i32> {
     input
 }
 
-fn get_histogram_of_joltage_jumps(adapter_chain: &Vec<i32>) -> HashMap<i32, i32> {
+fn get_histogram_of_joltage_jumps(adapters: &[i32], device_offset: i32) -> HashMap<i32, i32> {
     let mut histogram = HashMap::new();
 
-    // The difference b/w our starting joltage (0J) and our first adapter
-    histogram.insert(*adapter_chain.last().unwrap(), 1);
-    // The difference b/w our device and last adapter is always 3
-    histogram.insert(3, 1);
-
-
-    for (i, adapter) in adapter_chain[..adapter_chain.len()-1].iter().enumerate() {
-        let joltage_jump = adapter - adapter_chain[i+1];
-        let count = match histogram.get(&joltage_jump) {
-            None => 0,
-            Some(c) => c + 1
-        };
-        histogram.insert(joltage_jump, count);
+    // The chain includes the jumps from the outlet (0J) to the first adapter, and from the last
+    // adapter to our device
+    let chain = build_chain(adapters, device_offset);
+    for pair in chain.windows(2) {
+        let joltage_jump = pair[1] - pair[0];
+        *histogram.entry(joltage_jump).or_insert(0) += 1;
     }
 
     histogram
 }
 
 fn main() {
-    let mut input = get_input("input.txt");
-    input.sort();
-    input.reverse();
+    let input = get_input("input.txt");
 
-    let histogram = get_histogram_of_joltage_jumps(&input);
-    let final_answer = histogram.get(&1).unwrap() * histogram.get(&3).unwrap();
+    // Usage: day10 [max_jump] [device_offset]
+    let args: Vec<String> = env::args().collect();
+    let max_jump = args.get(1).map_or(3, |arg| arg.parse().unwrap());
+    let device_offset = args.get(2).map_or(3, |arg| arg.parse().unwrap());
+
+    if let Err(chain_break) = validate_chain(&input, max_jump, device_offset) {
+        println!("{}", chain_break);
+        return;
+    }
+
+    let histogram = get_histogram_of_joltage_jumps(&input, device_offset);
+    let final_answer = histogram.get(&1).unwrap_or(&0) * histogram.get(&3).unwrap_or(&0);
     println!("{}", final_answer);
+
+    let arrangements = count_arrangements(&input, max_jump, device_offset);
+    println!("Number of arrangements: {}", arrangements);
+    for arrangement in Arrangements::new(&input, max_jump, device_offset).take(3) {
+        println!("{:?}", arrangement);
+    }
+
+    let removable = removable_adapters(&input, max_jump, device_offset);
+    println!("Adapters that can be removed on their own: {:?}", removable);
 }
@@ -0,0 +1,86 @@
+use crate::adapter_chain::{count_arrangements, validate_chain, removable_adapters, ChainBreak, Arrangements};
+use crate::get_histogram_of_joltage_jumps;
+
+fn small_example() -> Vec<i32> {
+    vec![16, 10, 15, 5, 1, 11, 7, 19, 6, 12, 4]
+}
+
+fn large_example() -> Vec<i32> {
+    vec![
+        28, 33, 18, 42, 31, 14, 46, 20, 48, 47, 24, 23, 49, 45, 19, 38, 39, 11, 1, 32, 25, 35, 8,
+        17, 7, 9, 4, 2, 34, 10, 3,
+    ]
+}
+
+#[test]
+fn test_count_arrangements() {
+    assert_eq!(count_arrangements(&small_example(), 3, 3), 8);
+    assert_eq!(count_arrangements(&large_example(), 3, 3), 19208);
+}
+
+#[test]
+fn test_count_arrangements_with_custom_jump_and_offset() {
+    // The device can't be reached if it's rated further away than the maximum jump
+    assert_eq!(count_arrangements(&small_example(), 3, 4), 0);
+    // With jumps of at most 1J only the full chain 0-1-2-3 works
+    assert_eq!(count_arrangements(&[1, 2], 1, 1), 1);
+    // 0 -> {1, 2} -> 3 with jumps of up to 2J: 0-1-2-3, 0-1-3, 0-2-3
+    assert_eq!(count_arrangements(&[1, 2], 2, 1), 3);
+    assert_eq!(count_arrangements(&[], 3, 3), 1);
+}
+
+#[test]
+fn test_get_histogram_of_joltage_jumps() {
+    let histogram = get_histogram_of_joltage_jumps(&small_example(), 3);
+    assert_eq!(histogram.get(&1), Some(&7));
+    assert_eq!(histogram.get(&3), Some(&5));
+
+    let histogram = get_histogram_of_joltage_jumps(&large_example(), 3);
+    assert_eq!(histogram.get(&1), Some(&22));
+    assert_eq!(histogram.get(&3), Some(&10));
+}
+
+#[test]
+fn test_validate_chain() {
+    assert_eq!(validate_chain(&small_example(), 3, 3), Ok(()));
+
+    let chain_break = validate_chain(&[1, 2, 6, 7], 3, 3).unwrap_err();
+    assert_eq!(chain_break, ChainBreak { position: 3, from: 2, to: 6, max_jump: 3 });
+
+    let chain_break = validate_chain(&[4, 5], 3, 3).unwrap_err();
+    assert_eq!(chain_break, ChainBreak { position: 1, from: 0, to: 4, max_jump: 3 });
+
+    let chain_break = validate_chain(&[1, 2], 3, 5).unwrap_err();
+    assert_eq!(chain_break, ChainBreak { position: 3, from: 2, to: 7, max_jump: 3 });
+}
+
+#[test]
+fn test_removable_adapters() {
+    assert_eq!(removable_adapters(&small_example(), 3, 3), vec![5, 6, 11]);
+    assert_eq!(removable_adapters(&[3, 6, 9], 3, 3), vec![]);
+}
+
+#[test]
+fn test_arrangements() {
+    let arrangements: Vec<Vec<i32>> = Arrangements::new(&[1, 2, 3], 2, 1).collect();
+    assert_eq!(arrangements, vec![
+        vec![1, 2],
+        vec![1, 2, 3],
+        vec![1, 3],
+        vec![2],
+        vec![2, 3],
+    ]);
+    assert_eq!(count_arrangements(&[1, 2, 3], 2, 1), 5);
+
+    assert_eq!(Arrangements::new(&small_example(), 3, 3).count(), 8);
+    assert_eq!(Arrangements::new(&large_example(), 3, 3).count(), 19208);
+
+    let mut arrangements = Arrangements::new(&small_example(), 3, 3);
+    assert_eq!(arrangements.next().unwrap(), vec![1, 4, 5, 6, 7, 10, 11, 12, 15, 16, 19]);
+    assert_eq!(arrangements.next().unwrap(), vec![1, 4, 5, 6, 7, 10, 12, 15, 16, 19]);
+}
+
+#[test]
+fn test_arrangements_of_broken_chain() {
+    assert_eq!(Arrangements::new(&[1, 2, 6, 7], 3, 3).next(), None);
+}
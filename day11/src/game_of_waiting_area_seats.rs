@@ -1,9 +1,12 @@
 use crate::game_of_waiting_area_seats::PositionState::{EmptySeat, OccupiedSeat, Floor};
 use std::fmt::{Display, Formatter};
 use core::fmt;
+use std::mem;
 use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum PositionState {
     EmptySeat,
     OccupiedSeat,
@@ -20,7 +23,7 @@ impl PositionState {
         }
     }
 
-    fn to_char(&self) -> char {
+    fn to_char(self) -> char {
         match self {
             EmptySeat => 'L',
             OccupiedSeat => '#',
@@ -29,22 +32,154 @@ impl PositionState {
     }
 }
 
-type WaitingAreaMap = Vec<Vec<PositionState>>;
+/// Which seats a passenger pays attention to when deciding whether to sit down or get up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NeighbourStrategy {
+    /// The (up to) eight seats directly around a position
+    Adjacent,
+    /// The first seat visible in each of the eight directions, looking past floor
+    LineOfSight,
+}
+
+/// The rules of one variant of the seating game.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeatRule {
+    pub neighbour_strategy: NeighbourStrategy,
+    /// An empty seat becomes occupied when at most this many of its neighbours are occupied
+    pub occupy_at_most: usize,
+    /// An occupied seat is vacated when at least this many of its neighbours are occupied
+    pub vacate_at_least: usize,
+}
+
+impl SeatRule {
+    /// The rules from part 1 of the puzzle
+    pub fn adjacent() -> SeatRule {
+        SeatRule {
+            neighbour_strategy: NeighbourStrategy::Adjacent,
+            occupy_at_most: 0,
+            vacate_at_least: 4,
+        }
+    }
+
+    /// The rules from part 2 of the puzzle
+    pub fn line_of_sight() -> SeatRule {
+        SeatRule {
+            neighbour_strategy: NeighbourStrategy::LineOfSight,
+            occupy_at_most: 0,
+            vacate_at_least: 5,
+        }
+    }
+
+    pub fn should_occupy(&self, occupied_neighbours: usize) -> bool {
+        occupied_neighbours <= self.occupy_at_most
+    }
+
+    pub fn should_vacate(&self, occupied_neighbours: usize) -> bool {
+        occupied_neighbours >= self.vacate_at_least
+    }
+
+    /// Returns the next state of a position, and whether it's different from the current one.
+    pub fn next_state(&self, state: PositionState, occupied_neighbours: usize) -> (PositionState, bool) {
+        match state {
+            EmptySeat => {
+                if self.should_occupy(occupied_neighbours) {
+                    (OccupiedSeat, true)
+                } else {
+                    (EmptySeat, false)
+                }
+            }
+            OccupiedSeat => {
+                if self.should_vacate(occupied_neighbours) {
+                    (EmptySeat, true)
+                } else {
+                    (OccupiedSeat, false)
+                }
+            }
+            Floor => (Floor, false)
+        }
+    }
+}
+
+/// What lies beyond the edges of the floor plan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Topology {
+    /// Nothing; positions on the edges just have fewer neighbours
+    Bounded,
+    /// The opposite edge, so the floor plan wraps around like a torus
+    Toroidal,
+    /// A wall that counts as a permanently occupied seat
+    Walled,
+}
 
+/// What a passenger finds when looking in one direction.
+enum Sighting {
+    Seat(usize),
+    Wall,
+    Nothing,
+}
+
+/// How a simulation run came to an end.
+#[derive(Debug, PartialEq, Eq)]
+pub enum SimulationOutcome {
+    /// Nothing changes any more after this many ticks
+    Stabilized { ticks: usize },
+    /// The state after `entry_tick` ticks comes back every `period` ticks
+    Cycle { entry_tick: usize, period: usize },
+}
+
+const SLOPES: [(i32, i32); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1)
+];
+
+/// The seats that each position pays attention to under one neighbour strategy, computed once
+/// and stored flat. The neighbours of the position at index `i` are
+/// `neighbours[offsets[i]..offsets[i + 1]]`. Floor never changes, so only seats are listed.
+/// Walls never change either, so they're only kept as a count per position.
+#[derive(Debug)]
+struct NeighbourGraph {
+    strategy: NeighbourStrategy,
+    offsets: Vec<usize>,
+    neighbours: Vec<u32>,
+    walls: Vec<u8>,
+}
+
+impl NeighbourGraph {
+    fn neighbours_of(&self, index: usize) -> &[u32] {
+        &self.neighbours[self.offsets[index]..self.offsets[index + 1]]
+    }
+}
+
+/// The floor plan, stored row by row in a flat buffer. Ticking writes the next generation into a
+/// second buffer of the same size and swaps the two, so nothing is allocated while simulating.
+///
+/// Alongside the neighbour graph we keep the number of occupied neighbours of every position,
+/// which only needs updating around the seats that changed in the last tick.
 #[derive(Debug)]
 pub struct WaitingArea {
-    pub map: WaitingAreaMap,
+    cells: Vec<PositionState>,
+    next_cells: Vec<PositionState>,
+    neighbour_graph: Option<NeighbourGraph>,
+    occupied_neighbours: Vec<u8>,
+    changed_cells: Vec<usize>,
+    topology: Topology,
     pub width: usize,
     pub height: usize,
 }
 
 impl Display for WaitingArea {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        for row in self.map.iter() {
+        for row in self.cells.chunks(self.width) {
             for col in row {
-                write!(f, "{}", col.to_char());
+                write!(f, "{}", col.to_char())?;
             }
-            writeln!(f, "");
+            writeln!(f)?;
         }
 
         fmt::Result::Ok(())
@@ -52,253 +187,234 @@ impl Display for WaitingArea {
 }
 
 impl WaitingArea {
-    pub fn new(input: &Vec<String>) -> WaitingArea {
+    pub fn new(input: &[String]) -> WaitingArea {
         let height = input.len();
         let width = input[0].len();
 
-        let mut waiting_area = WaitingArea {
-            map: vec![],
-            width,
-            height,
-        };
-
-        for y in 0..height {
-            let mut row: Vec<PositionState> = vec![];
-            let row_chars = input[y].as_bytes();
-
-            for x in 0..width {
-                let position_state = PositionState::from_input_char(
-                    row_chars[x] as char
-                );
-                row.push(position_state);
+        let mut cells = Vec::with_capacity(width * height);
+        for line in input {
+            for c in line.chars().take(width) {
+                cells.push(PositionState::from_input_char(c));
             }
-
-            waiting_area.map.push(row);
         }
 
-        waiting_area
+        WaitingArea {
+            next_cells: cells.clone(),
+            cells,
+            neighbour_graph: None,
+            occupied_neighbours: vec![],
+            changed_cells: vec![],
+            topology: Topology::Bounded,
+            width,
+            height,
+        }
     }
 
-    pub fn get_number_of_occupied_seats(&self) -> usize {
-        let mut occupied_seats = 0;
-        for row in self.map.iter() {
-            for position_state in row.iter() {
-                match *position_state {
-                    EmptySeat => {}
-                    OccupiedSeat => { occupied_seats += 1; }
-                    Floor => {}
-                }
-            }
-        }
+    pub fn get(&self, x: usize, y: usize) -> PositionState {
+        self.cells[self.index(x, y)]
+    }
 
-        occupied_seats
+    fn index(&self, x: usize, y: usize) -> usize {
+        y * self.width + x
     }
 
-    pub fn get_next_state(&self, x: usize, y: usize) -> (PositionState, bool) {
-        let neighbours = self.get_neighbours(x, y);
+    pub fn set_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+        self.neighbour_graph = None;
+    }
 
-        let mut state_counter = HashMap::new();
-        state_counter.insert(&Floor, 0);
-        state_counter.insert(&EmptySeat, 0);
-        state_counter.insert(&OccupiedSeat, 0);
+    /// Returns the position at `(x + dx, y + dy)`, or `None` if that's over the edge of a map
+    /// that doesn't wrap around.
+    fn step(&self, x: usize, y: usize, (dx, dy): (i32, i32)) -> Option<(usize, usize)> {
+        let x = x as i32 + dx;
+        let y = y as i32 + dy;
+        let width = self.width as i32;
+        let height = self.height as i32;
 
-        for neighbour in neighbours.iter() {
-            let neighbour_states_count = match state_counter.get(neighbour) {
-                Some(count) => count + 1,
-                None => 0
-            };
-            state_counter.insert(neighbour, neighbour_states_count);
+        if self.topology == Topology::Toroidal {
+            return Some((x.rem_euclid(width) as usize, y.rem_euclid(height) as usize));
         }
 
-        match self.map[y][x] {
-            EmptySeat => {
-                if *state_counter.get(&OccupiedSeat).unwrap() == 0 {
-                    (OccupiedSeat, true)
-                } else {
-                    (EmptySeat, false)
-                }
-            }
-            OccupiedSeat => {
-                if *state_counter.get(&OccupiedSeat).unwrap() >= 4 {
-                    (EmptySeat, true)
-                } else {
-                    (OccupiedSeat, false)
-                }
-            }
-            Floor => (Floor, false)
+        if x < 0 || x >= width {
+            return None;
         }
-    }
-
-    pub fn get_next_state2(&self, x: usize, y: usize) -> (PositionState, bool) {
-        let neighbours = self.get_visible_neighbours(x, y);
-
-        let mut state_counter = HashMap::new();
-        state_counter.insert(&Floor, 0);
-        state_counter.insert(&EmptySeat, 0);
-        state_counter.insert(&OccupiedSeat, 0);
-
-        for neighbour in neighbours.iter() {
-            let neighbour_states_count = match state_counter.get(neighbour) {
-                Some(count) => count + 1,
-                None => 0
-            };
-            state_counter.insert(neighbour, neighbour_states_count);
+        if y < 0 || y >= height {
+            return None;
         }
 
-        match self.map[y][x] {
-            EmptySeat => {
-                if *state_counter.get(&OccupiedSeat).unwrap() == 0 {
-                    (OccupiedSeat, true)
-                } else {
-                    (EmptySeat, false)
-                }
-            }
-            OccupiedSeat => {
-                if *state_counter.get(&OccupiedSeat).unwrap() >= 5 {
-                    (EmptySeat, true)
-                } else {
-                    (OccupiedSeat, false)
-                }
-            }
-            Floor => (Floor, false)
+        Some((x as usize, y as usize))
+    }
+
+    fn edge(&self) -> Sighting {
+        match self.topology {
+            Topology::Walled => Sighting::Wall,
+            _ => Sighting::Nothing,
         }
     }
 
+    /// All positions, row by row
+    pub fn cells(&self) -> &[PositionState] {
+        &self.cells
+    }
 
-    pub fn get_first_visible_neighbour_from_with_slope(&self, x: usize, y: usize, slope: (i32, i32)) -> PositionState {
-        let mut x = x as i32;
-        let mut y = y as i32;
+    pub fn get_number_of_occupied_seats(&self) -> usize {
+        self.cells.iter()
+            .filter(|position_state| **position_state == OccupiedSeat)
+            .count()
+    }
 
-        let (dx, dy) = slope;
+    fn look(&self, x: usize, y: usize, slope: (i32, i32), strategy: NeighbourStrategy) -> Sighting {
+        let mut position = (x, y);
 
-        loop {
-            x += dx;
-            y += dy;
+        // On a torus a line of floor can lead all the way around, so give up once it could have
+        // visited every position
+        for _ in 0..self.width * self.height {
+            position = match self.step(position.0, position.1, slope) {
+                Some(position) => position,
+                None => return self.edge(),
+            };
+            if position == (x, y) {
+                return Sighting::Nothing;
+            }
 
-            if x < 0 || x >= self.width as i32 {
-                break;
+            let index = self.index(position.0, position.1);
+            if self.cells[index] != Floor {
+                return Sighting::Seat(index);
             }
-            if y < 0 || y >= self.height as i32 {
+            if strategy == NeighbourStrategy::Adjacent {
                 break;
             }
-
-            match &self.map[y as usize][x as usize] {
-                Floor => {
-                    continue;
-                },
-                x => { return x.clone(); }
-            }
         }
 
-        Floor
+        Sighting::Nothing
     }
 
-    pub fn get_visible_neighbours(&self, x: usize, y: usize) -> Vec<PositionState> {
-        let slopes = [
-            (-1, -1),
-            (0, -1),
-            (1, -1),
-            (-1, 0),
-            (1, 0),
-            (-1, 1),
-            (0, 1),
-            (1, 1)
-        ];
-
-        slopes.iter().map(|slope| {
-            self.get_first_visible_neighbour_from_with_slope(x, y, slope.clone())
-        }).collect()
+    /// Returns the indices of the seats the position at `(x, y)` pays attention to, and how many
+    /// walls it sees.
+    fn get_neighbour_seats(&self, x: usize, y: usize, strategy: NeighbourStrategy) -> (Vec<usize>, u8) {
+        let mut seats = vec![];
+        let mut walls = 0;
+
+        for slope in SLOPES.iter() {
+            match self.look(x, y, *slope, strategy) {
+                Sighting::Seat(index) => seats.push(index),
+                Sighting::Wall => walls += 1,
+                Sighting::Nothing => {}
+            }
+        }
+
+        (seats, walls)
     }
 
-    pub fn get_neighbours(&self, x: usize, y: usize) -> Vec<PositionState> {
-        let x = x as i32;
-        let y = y as i32;
-        let all_neighbours = vec![
-            (x - 1, y - 1),
-            (x, y - 1),
-            (x + 1, y - 1),
-            (x - 1, y),
-            (x + 1, y),
-            (x - 1, y + 1),
-            (x, y + 1),
-            (x + 1, y + 1)
-        ];
-        let valid_neighbours = all_neighbours.iter().filter(|n| {
-            let (x, y) = n;
-            let x = *x;
-            let y = *y;
-
-            if x < 0 || x >= self.width as i32 {
-                return false;
-            }
+    fn build_neighbour_graph(&self, strategy: NeighbourStrategy) -> NeighbourGraph {
+        let mut offsets = Vec::with_capacity(self.cells.len() + 1);
+        let mut neighbours = vec![];
+        let mut walls = vec![0; self.cells.len()];
 
-            if y < 0 || y >= self.height as i32 {
-                return false;
+        offsets.push(0);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if self.get(x, y) != Floor {
+                    let (seats, walls_seen) = self.get_neighbour_seats(x, y, strategy);
+                    neighbours.extend(seats.iter().map(|index| *index as u32));
+                    walls[self.index(x, y)] = walls_seen;
+                }
+                offsets.push(neighbours.len());
             }
+        }
 
-            true
-        });
-
-        let mut neighbours: Vec<PositionState> = vec![];
-        for (x, y) in valid_neighbours {
-            let x = *x as usize;
-            let y = *y as usize;
+        NeighbourGraph { strategy, offsets, neighbours, walls }
+    }
 
-            let position_state = &self.map[y][x];
-            neighbours.push(
-                position_state.clone()
-            );
+    /// Builds the neighbour graph for `strategy`, unless that's the one we already have.
+    fn use_neighbour_strategy(&mut self, strategy: NeighbourStrategy) {
+        if self.neighbour_graph.as_ref().map(|graph| graph.strategy) != Some(strategy) {
+            let neighbour_graph = self.build_neighbour_graph(strategy);
+            self.occupied_neighbours = (0..self.cells.len()).map(|index| {
+                neighbour_graph.neighbours_of(index)
+                    .iter()
+                    .filter(|neighbour| self.cells[**neighbour as usize] == OccupiedSeat)
+                    .count() as u8 + neighbour_graph.walls[index]
+            }).collect();
+            self.neighbour_graph = Some(neighbour_graph);
         }
-        neighbours
     }
 
-    pub fn tick(&mut self) -> bool {
-        let mut next_map = vec![];
-        let mut did_map_change = false;
+    pub fn tick(&mut self, rule: &SeatRule) -> bool {
+        self.use_neighbour_strategy(rule.neighbour_strategy);
+        let neighbour_graph = self.neighbour_graph.as_ref().unwrap();
 
-        for y in 0..self.height {
-            let mut row = vec![];
+        self.changed_cells.clear();
+        for (index, position_state) in self.cells.iter().enumerate() {
+            let occupied_neighbours = self.occupied_neighbours[index] as usize;
+            let (next_state, did_change) = rule.next_state(*position_state, occupied_neighbours);
+            self.next_cells[index] = next_state;
 
-            for x in 0..self.width {
-                let (next_state, did_change) = self.get_next_state(x, y);
-                row.push(
-                    next_state
-                );
+            if did_change {
+                self.changed_cells.push(index);
+            }
+        }
 
-                if did_change {
-                    did_map_change = true;
+        for index in self.changed_cells.iter() {
+            let was_occupied = self.cells[*index] == OccupiedSeat;
+            for neighbour in neighbour_graph.neighbours_of(*index) {
+                let count = &mut self.occupied_neighbours[*neighbour as usize];
+                if was_occupied {
+                    *count -= 1;
+                } else {
+                    *count += 1;
                 }
             }
-
-            next_map.push(row);
         }
 
-        self.map = next_map;
-        did_map_change
+        mem::swap(&mut self.cells, &mut self.next_cells);
+        !self.changed_cells.is_empty()
     }
 
-    pub fn tick2(&mut self) -> bool {
-        let mut next_map = vec![];
-        let mut did_map_change = false;
-
-        for y in 0..self.height {
-            let mut row = vec![];
-
-            for x in 0..self.width {
-                let (next_state, did_change) = self.get_next_state2(x, y);
-                row.push(
-                    next_state
-                );
+    fn hash_cells(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.cells.hash(&mut hasher);
+        hasher.finish()
+    }
 
-                if did_change {
-                    did_map_change = true;
-                }
+    /// Ticks until the waiting area either stabilizes or starts repeating itself, calling
+    /// `on_tick` after every tick. Earlier states are looked up by their hash, but a cycle is
+    /// only reported once the cells themselves match, so hash collisions can't fake one.
+    pub fn run<F: FnMut(&WaitingArea)>(&mut self, rule: &SeatRule, mut on_tick: F) -> SimulationOutcome {
+        let mut seen_states = vec![self.cells.clone()];
+        let mut ticks_by_hash: HashMap<u64, Vec<usize>> = HashMap::new();
+        ticks_by_hash.insert(self.hash_cells(), vec![0]);
+
+        let mut ticks = 0;
+        while self.tick(rule) {
+            ticks += 1;
+            on_tick(self);
+
+            let same_hash = ticks_by_hash.entry(self.hash_cells()).or_default();
+            let entry_tick = same_hash.iter().find(|tick| seen_states[**tick] == self.cells);
+            if let Some(entry_tick) = entry_tick {
+                return SimulationOutcome::Cycle { entry_tick: *entry_tick, period: ticks - entry_tick };
             }
 
-            next_map.push(row);
+            same_hash.push(ticks);
+            seen_states.push(self.cells.clone());
         }
 
-        self.map = next_map;
-        did_map_change
+        SimulationOutcome::Stabilized { ticks }
     }
-}
\ No newline at end of file
+}
+
+/// Looks into the neighbour graph the simulation runs on.
+#[cfg(test)]
+impl WaitingArea {
+    /// The indices of the seats the position at `(x, y)` pays attention to, and how many walls it
+    /// sees.
+    pub fn get_neighbour_graph_entry(&mut self, x: usize, y: usize, strategy: NeighbourStrategy) -> (Vec<usize>, u8) {
+        self.use_neighbour_strategy(strategy);
+        let neighbour_graph = self.neighbour_graph.as_ref().unwrap();
+        let index = self.index(x, y);
+
+        let seats = neighbour_graph.neighbours_of(index).iter().map(|index| *index as usize).collect();
+        (seats, neighbour_graph.walls[index])
+    }
+}
@@ -1,11 +1,19 @@
 use std::path::Path;
 use std::fs::File;
 use std::io::{BufReader, BufRead};
-use crate::game_of_waiting_area_seats::WaitingArea;
+use std::env;
+use std::process;
+use crate::game_of_waiting_area_seats::{WaitingArea, SeatRule, NeighbourStrategy, Topology, SimulationOutcome};
+use crate::seating_export::SeatingRecording;
 
 mod game_of_waiting_area_seats;
+mod seating_export;
+#[cfg(test)]
 mod tests;
 
+const USAGE: &str = "Usage: day11 [--neighbours adjacent|sight] [--occupy N] [--vacate N] \
+[--topology bounded|torus|walls] [--gif FILE | --frames DIRECTORY] [--scale PIXELS] [input file]";
+
 fn get_input(filename: &str) -> Vec<String> {
     let path = Path::new(filename);
     let file = File::open(path).unwrap();
@@ -19,15 +27,125 @@ fn get_input(filename: &str) -> Vec<String> {
     input
 }
 
+enum Export {
+    Gif(String),
+    PngFrames(String),
+}
+
+struct Options {
+    filename: String,
+    rule: SeatRule,
+    topology: Topology,
+    export: Option<Export>,
+    scale: u32,
+}
+
+/// Builds the seat rule from the command line. Picking a neighbour strategy starts from the
+/// matching puzzle rule, and `--occupy`/`--vacate` then override its thresholds. Without any
+/// options we get part 2 of the puzzle.
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut filename = String::from("input.txt");
+    let mut neighbour_strategy = NeighbourStrategy::LineOfSight;
+    let mut occupy_at_most = None;
+    let mut vacate_at_least = None;
+    let mut topology = Topology::Bounded;
+    let mut export = None;
+    let mut scale = 4;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match &arg[..] {
+            "--neighbours" => {
+                neighbour_strategy = match args.next().map(|value| &value[..]) {
+                    Some("adjacent") => NeighbourStrategy::Adjacent,
+                    Some("sight") => NeighbourStrategy::LineOfSight,
+                    _ => return Err(String::from("--neighbours expects adjacent or sight")),
+                }
+            }
+            "--topology" => {
+                topology = match args.next().map(|value| &value[..]) {
+                    Some("bounded") => Topology::Bounded,
+                    Some("torus") => Topology::Toroidal,
+                    Some("walls") => Topology::Walled,
+                    _ => return Err(String::from("--topology expects bounded, torus or walls")),
+                }
+            }
+            "--occupy" => occupy_at_most = Some(parse_threshold(arg, args.next())?),
+            "--vacate" => vacate_at_least = Some(parse_threshold(arg, args.next())?),
+            "--gif" => export = Some(Export::Gif(parse_path(arg, args.next())?)),
+            "--frames" => export = Some(Export::PngFrames(parse_path(arg, args.next())?)),
+            "--scale" => {
+                scale = args.next()
+                    .and_then(|value| value.parse().ok())
+                    .filter(|scale| *scale > 0)
+                    .ok_or_else(|| String::from("--scale expects a positive number of pixels"))?
+            }
+            _ => filename = arg.clone(),
+        }
+    }
+
+    let mut rule = match neighbour_strategy {
+        NeighbourStrategy::Adjacent => SeatRule::adjacent(),
+        NeighbourStrategy::LineOfSight => SeatRule::line_of_sight(),
+    };
+    if let Some(occupy_at_most) = occupy_at_most {
+        rule.occupy_at_most = occupy_at_most;
+    }
+    if let Some(vacate_at_least) = vacate_at_least {
+        rule.vacate_at_least = vacate_at_least;
+    }
+
+    Ok(Options { filename, rule, topology, export, scale })
+}
+
+fn parse_threshold(option: &str, value: Option<&String>) -> Result<usize, String> {
+    value
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| format!("{} expects a number of neighbours", option))
+}
+
+fn parse_path(option: &str, value: Option<&String>) -> Result<String, String> {
+    value.cloned().ok_or_else(|| format!("{} expects a path", option))
+}
+
 fn main() {
-    let input = get_input("input.txt");
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}\n{}", error, USAGE);
+            process::exit(1);
+        }
+    };
+
+    let input = get_input(&options.filename);
     let mut waiting_area = WaitingArea::new(&input);
-    let mut ticks = 0;
-    while waiting_area.tick2() {
-        ticks += 1;
-        if ticks % 100 == 0 {
-            println!("Ticks: {}", ticks);
+    waiting_area.set_topology(options.topology);
+
+    let outcome = match options.export {
+        Some(export) => {
+            let recording = SeatingRecording::record(&mut waiting_area, &options.rule);
+            let result = match &export {
+                Export::Gif(path) => recording.write_gif(Path::new(path), options.scale, 100),
+                Export::PngFrames(directory) => recording.write_png_frames(Path::new(directory), options.scale),
+            };
+            if let Err(error) = result {
+                eprintln!("Export failed: {}", error);
+                process::exit(1);
+            }
+
+            recording.outcome
+        }
+        None => waiting_area.run(&options.rule, |_| {}),
+    };
+
+    match outcome {
+        SimulationOutcome::Stabilized { ticks } => {
+            println!("Stabilized after {} ticks", ticks);
+            println!("Occupied seats after stabilization: {}", waiting_area.get_number_of_occupied_seats());
+        }
+        SimulationOutcome::Cycle { entry_tick, period } => {
+            println!("Never stabilizes: from tick {} on, the seating repeats every {} ticks", entry_tick, period);
         }
     }
-    println!("Occupied seats after stabilization: {}", waiting_area.get_number_of_occupied_seats());
 }
@@ -0,0 +1,131 @@
+use crate::game_of_waiting_area_seats::{WaitingArea, SeatRule, PositionState, SimulationOutcome};
+use image::{Rgb, RgbImage, Frame, Delay, ImageResult, ImageError, DynamicImage};
+use image::codecs::gif::{GifEncoder, Repeat};
+use std::fs::{self, File};
+use std::path::Path;
+
+const FLOOR_COLOR: Rgb<u8> = Rgb([64, 64, 64]);
+const EMPTY_SEAT_COLOR: Rgb<u8> = Rgb([90, 170, 90]);
+const OCCUPIED_SEAT_COLOR: Rgb<u8> = Rgb([210, 60, 60]);
+
+const CHART_BACKGROUND_COLOR: Rgb<u8> = Rgb([255, 255, 255]);
+const CHART_AXIS_COLOR: Rgb<u8> = Rgb([0, 0, 0]);
+const CHART_BAR_COLOR: Rgb<u8> = OCCUPIED_SEAT_COLOR;
+const CHART_MARGIN: u32 = 10;
+
+fn color_of(position_state: PositionState) -> Rgb<u8> {
+    match position_state {
+        PositionState::Floor => FLOOR_COLOR,
+        PositionState::EmptySeat => EMPTY_SEAT_COLOR,
+        PositionState::OccupiedSeat => OCCUPIED_SEAT_COLOR,
+    }
+}
+
+/// Every state a waiting area goes through on its way to stabilizing, starting with the initial
+/// one. If it never stabilizes, the recording ends once the first cycle has gone round.
+pub struct SeatingRecording {
+    pub width: usize,
+    pub height: usize,
+    pub frames: Vec<Vec<PositionState>>,
+    pub outcome: SimulationOutcome,
+}
+
+impl SeatingRecording {
+    pub fn record(waiting_area: &mut WaitingArea, rule: &SeatRule) -> SeatingRecording {
+        let mut frames = vec![waiting_area.cells().to_vec()];
+        let outcome = waiting_area.run(rule, |waiting_area| {
+            frames.push(waiting_area.cells().to_vec());
+        });
+
+        SeatingRecording {
+            width: waiting_area.width,
+            height: waiting_area.height,
+            frames,
+            outcome,
+        }
+    }
+
+    pub fn occupied_seats_per_tick(&self) -> Vec<usize> {
+        self.frames.iter().map(|frame| {
+            frame.iter().filter(|position_state| **position_state == PositionState::OccupiedSeat).count()
+        }).collect()
+    }
+
+    /// Draws one frame, with every position as a `scale` x `scale` square.
+    pub fn render_frame(&self, tick: usize, scale: u32) -> RgbImage {
+        let frame = &self.frames[tick];
+        let width = self.width as u32;
+
+        RgbImage::from_fn(width * scale, self.height as u32 * scale, |x, y| {
+            let index = (y / scale) * width + x / scale;
+            color_of(frame[index as usize])
+        })
+    }
+
+    /// Draws a bar per tick, each as high as the number of seats occupied after that tick.
+    pub fn render_occupancy_chart(&self, width: u32, height: u32) -> RgbImage {
+        let mut chart = RgbImage::from_pixel(width, height, CHART_BACKGROUND_COLOR);
+        let occupied_seats = self.occupied_seats_per_tick();
+
+        let plot_width = width.saturating_sub(2 * CHART_MARGIN);
+        let plot_height = height.saturating_sub(2 * CHART_MARGIN);
+        let bottom = height.saturating_sub(CHART_MARGIN);
+        let most_occupied_seats = occupied_seats.iter().copied().max().unwrap_or(0).max(1);
+        let bar_width = (plot_width / occupied_seats.len() as u32).max(1);
+
+        for (tick, seats) in occupied_seats.iter().enumerate() {
+            let bar_height = (*seats as u64 * plot_height as u64 / most_occupied_seats as u64) as u32;
+            let left = CHART_MARGIN + tick as u32 * bar_width;
+
+            // Leave a pixel between bars when there's room for it
+            let right = if bar_width > 2 { left + bar_width - 1 } else { left + bar_width };
+            for x in left..right.min(width) {
+                for y in bottom - bar_height..bottom {
+                    chart.put_pixel(x, y, CHART_BAR_COLOR);
+                }
+            }
+        }
+
+        for x in CHART_MARGIN..width.saturating_sub(CHART_MARGIN) {
+            chart.put_pixel(x, bottom, CHART_AXIS_COLOR);
+        }
+        for y in CHART_MARGIN..=bottom {
+            chart.put_pixel(CHART_MARGIN, y, CHART_AXIS_COLOR);
+        }
+
+        chart
+    }
+
+    fn render_chart_for_frames(&self, scale: u32) -> RgbImage {
+        let width = (self.width as u32 * scale).max(200);
+        self.render_occupancy_chart(width, width / 2)
+    }
+
+    /// Writes an endlessly looping GIF of the seating evolution, and the occupancy chart as a PNG
+    /// next to it.
+    pub fn write_gif(&self, path: &Path, scale: u32, frame_delay_ms: u32) -> ImageResult<()> {
+        let mut encoder = GifEncoder::new(File::create(path).map_err(ImageError::IoError)?);
+        encoder.set_repeat(Repeat::Infinite)?;
+
+        let delay = Delay::from_numer_denom_ms(frame_delay_ms, 1);
+        let frames = (0..self.frames.len()).map(|tick| {
+            let image = DynamicImage::ImageRgb8(self.render_frame(tick, scale)).into_rgba8();
+            Frame::from_parts(image, 0, 0, delay)
+        });
+        encoder.encode_frames(frames)?;
+
+        self.render_chart_for_frames(scale).save(path.with_extension("occupancy.png"))
+    }
+
+    /// Writes every frame as a numbered PNG into `directory`, along with `occupancy.png`.
+    pub fn write_png_frames(&self, directory: &Path, scale: u32) -> ImageResult<()> {
+        fs::create_dir_all(directory).map_err(ImageError::IoError)?;
+
+        for tick in 0..self.frames.len() {
+            let path = directory.join(format!("tick_{:04}.png", tick));
+            self.render_frame(tick, scale).save(path)?;
+        }
+
+        self.render_chart_for_frames(scale).save(directory.join("occupancy.png"))
+    }
+}
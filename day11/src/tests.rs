@@ -1,4 +1,5 @@
-use crate::game_of_waiting_area_seats::{WaitingArea, PositionState};
+use crate::game_of_waiting_area_seats::{WaitingArea, SeatRule, NeighbourStrategy, Topology, SimulationOutcome};
+use crate::seating_export::SeatingRecording;
 use crate::game_of_waiting_area_seats::PositionState::{Floor, EmptySeat, OccupiedSeat};
 
 fn waiting_area_with_sample_input() -> WaitingArea {
@@ -25,47 +26,46 @@ fn test_waiting_area_creation() {
 }
 
 #[test]
-fn test_get_neighbours() {
-    let waiting_area = waiting_area_with_sample_input();
-    let neighbours = waiting_area.get_neighbours(0, 0);
-    let mut neighbours = neighbours.iter();
-    let expected_output = [Floor, EmptySeat, EmptySeat];
+fn test_neighbour_graph() {
+    let mut waiting_area = waiting_area_with_sample_input();
 
-    for to_expect in expected_output.iter() {
-        assert_eq!(neighbours.next().unwrap(), to_expect);
-    }
+    // (1, 0) is floor, which leaves the seats at (0, 1) and (1, 1)
+    assert_eq!(waiting_area.get_neighbour_graph_entry(0, 0, NeighbourStrategy::Adjacent), (vec![10, 11], 0));
 
-    assert_eq!(neighbours.next(), None);
+    // Looking right from (0, 2) goes past the floor at (1, 2) to the seat at (2, 2)
+    let (seats, _) = waiting_area.get_neighbour_graph_entry(0, 2, NeighbourStrategy::LineOfSight);
+    assert_eq!(seats, vec![10, 11, 22, 30, 31]);
 }
 
 #[test]
-fn test_get_next_state() {
-    let waiting_area = waiting_area_with_sample_input();
-    let (next_state, _) = waiting_area.get_next_state(0, 0);
+fn test_first_tick() {
+    let mut waiting_area = waiting_area_with_sample_input();
+    assert!(waiting_area.tick(&SeatRule::adjacent()));
 
-    assert_eq!(OccupiedSeat, next_state);
+    assert_eq!(waiting_area.get(0, 0), OccupiedSeat);
+    assert_eq!(waiting_area.get(1, 0), Floor);
 }
 
 #[test]
 fn test_tick() {
     let mut waiting_area = waiting_area_with_sample_input();
-    assert_eq!(waiting_area.tick(), true);
-    assert_eq!(waiting_area.tick(), true);
+    assert!(waiting_area.tick(&SeatRule::adjacent()));
+    assert!(waiting_area.tick(&SeatRule::adjacent()));
 
-    assert_eq!(waiting_area.map[0][2], EmptySeat);
+    assert_eq!(waiting_area.get(2, 0), EmptySeat);
 
-    assert_eq!(waiting_area.tick(), true);
-    assert_eq!(waiting_area.map[0][2], OccupiedSeat);
+    assert!(waiting_area.tick(&SeatRule::adjacent()));
+    assert_eq!(waiting_area.get(2, 0), OccupiedSeat);
 
-    assert_eq!(waiting_area.tick(), true);
-    assert_eq!(waiting_area.tick(), true);
-    assert_eq!(waiting_area.tick(), false);
+    assert!(waiting_area.tick(&SeatRule::adjacent()));
+    assert!(waiting_area.tick(&SeatRule::adjacent()));
+    assert!(!waiting_area.tick(&SeatRule::adjacent()));
 
     assert_eq!(waiting_area.get_number_of_occupied_seats(), 37);
 }
 
 #[test]
-fn test_get_first_visible_neighbour_from_with_slope() {
+fn test_line_of_sight_neighbours() {
     let input = vec![
         String::from(".##.##."),
         String::from("#.#.#.#"),
@@ -77,10 +77,8 @@ fn test_get_first_visible_neighbour_from_with_slope() {
     ];
     let mut waiting_area = WaitingArea::new(&input);
 
-    assert_eq!(
-        waiting_area.get_first_visible_neighbour_from_with_slope(3, 3, (-1, -1)),
-        Floor
-    );
+    // The seat in the middle can't see any of the others
+    assert_eq!(waiting_area.get_neighbour_graph_entry(3, 3, NeighbourStrategy::LineOfSight), (vec![], 0));
 
     let input = vec![
         String::from(".......#."),
@@ -95,15 +93,212 @@ fn test_get_first_visible_neighbour_from_with_slope() {
     ];
     let mut waiting_area = WaitingArea::new(&input);
 
-    assert_eq!(
-        waiting_area.get_first_visible_neighbour_from_with_slope(3, 4, (-1, -1)),
-        OccupiedSeat
-    );
+    // ... while this one sees an occupied seat in every direction, starting with (1, 2) up-left
+    let (seats, _) = waiting_area.get_neighbour_graph_entry(3, 4, NeighbourStrategy::LineOfSight);
+    assert_eq!(seats.len(), 8);
+    assert_eq!(seats[0], 2 * 9 + 1);
+    assert!(seats.iter().all(|index| waiting_area.cells()[*index] == OccupiedSeat));
+
+    waiting_area.tick(&SeatRule::line_of_sight());
+    assert_eq!(waiting_area.get(3, 4), EmptySeat);
 }
 
 #[test]
 fn test_tick2() {
     let mut waiting_area = waiting_area_with_sample_input();
-    while waiting_area.tick2(){}
+    while waiting_area.tick(&SeatRule::line_of_sight()) {}
     assert_eq!(waiting_area.get_number_of_occupied_seats(), 26);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_custom_seat_rule() {
+    let rule = SeatRule {
+        neighbour_strategy: NeighbourStrategy::Adjacent,
+        occupy_at_most: 0,
+        vacate_at_least: 5,
+    };
+    let mut waiting_area = waiting_area_with_sample_input();
+    while waiting_area.tick(&rule) {}
+    assert_eq!(waiting_area.get_number_of_occupied_seats(), 46);
+
+    // Nobody ever gets up, so every seat fills up on the first tick
+    let rule = SeatRule {
+        neighbour_strategy: NeighbourStrategy::LineOfSight,
+        occupy_at_most: 8,
+        vacate_at_least: 9,
+    };
+    let mut waiting_area = waiting_area_with_sample_input();
+    assert!(waiting_area.tick(&rule));
+    assert!(!waiting_area.tick(&rule));
+    assert_eq!(waiting_area.get_number_of_occupied_seats(), 71);
+}
+
+#[test]
+fn test_parse_args() {
+    let args: Vec<String> = vec![];
+    let options = crate::parse_args(&args).unwrap();
+    assert_eq!(options.rule, SeatRule::line_of_sight());
+    assert_eq!(options.filename, "input.txt");
+
+    let args: Vec<String> = ["--neighbours", "adjacent", "--vacate", "6", "other.txt"]
+        .iter().map(|arg| arg.to_string()).collect();
+    let options = crate::parse_args(&args).unwrap();
+    assert_eq!(options.rule.neighbour_strategy, NeighbourStrategy::Adjacent);
+    assert_eq!(options.rule.occupy_at_most, 0);
+    assert_eq!(options.rule.vacate_at_least, 6);
+    assert_eq!(options.filename, "other.txt");
+
+    let args: Vec<String> = vec![String::from("--occupy")];
+    assert!(crate::parse_args(&args).is_err());
+}
+
+#[test]
+fn test_switching_rules_rebuilds_neighbours() {
+    let mut waiting_area = waiting_area_with_sample_input();
+    while waiting_area.tick(&SeatRule::adjacent()) {}
+
+    // Starting over from the stabilized layout has to give the same result as carrying on
+    let layout: Vec<String> = waiting_area.to_string().lines().map(String::from).collect();
+    let mut restarted_waiting_area = WaitingArea::new(&layout);
+
+    for _ in 0..3 {
+        waiting_area.tick(&SeatRule::line_of_sight());
+        restarted_waiting_area.tick(&SeatRule::line_of_sight());
+        assert_eq!(waiting_area.to_string(), restarted_waiting_area.to_string());
+    }
+}
+
+#[test]
+fn test_seating_recording() {
+    let mut waiting_area = waiting_area_with_sample_input();
+    let recording = SeatingRecording::record(&mut waiting_area, &SeatRule::adjacent());
+
+    assert_eq!(recording.frames.len(), 6);
+    assert_eq!(recording.occupied_seats_per_tick(), vec![0, 71, 20, 51, 30, 37]);
+}
+
+#[test]
+fn test_render_frame() {
+    let mut waiting_area = waiting_area_with_sample_input();
+    let recording = SeatingRecording::record(&mut waiting_area, &SeatRule::adjacent());
+
+    let frame = recording.render_frame(1, 3);
+    assert_eq!(frame.dimensions(), (30, 30));
+    // (0, 0) is an occupied seat and (1, 0) is floor
+    assert_eq!(frame.get_pixel(2, 2), frame.get_pixel(0, 0));
+    assert_ne!(frame.get_pixel(3, 0), frame.get_pixel(0, 0));
+
+    // (2, 0) is occupied after the first tick and empty again after the second
+    let next_frame = recording.render_frame(2, 3);
+    assert_eq!(frame.get_pixel(6, 0), frame.get_pixel(0, 0));
+    assert_ne!(next_frame.get_pixel(6, 0), frame.get_pixel(0, 0));
+    assert_ne!(next_frame.get_pixel(6, 0), frame.get_pixel(3, 0));
+}
+
+#[test]
+fn test_render_occupancy_chart() {
+    let mut waiting_area = waiting_area_with_sample_input();
+    let recording = SeatingRecording::record(&mut waiting_area, &SeatRule::adjacent());
+
+    let chart = recording.render_occupancy_chart(80, 60);
+    assert_eq!(chart.dimensions(), (80, 60));
+
+    // The tallest bar (tick 1) reaches the top of the plot, the first one (no seats) is empty
+    let background = *chart.get_pixel(79, 0);
+    assert_ne!(*chart.get_pixel(25, 10), background);
+    assert_eq!(*chart.get_pixel(15, 48), background);
+}
+
+#[test]
+fn test_write_png_frames() {
+    let mut waiting_area = waiting_area_with_sample_input();
+    let recording = SeatingRecording::record(&mut waiting_area, &SeatRule::adjacent());
+
+    let directory = std::env::temp_dir().join("day11_test_write_png_frames");
+    recording.write_png_frames(&directory, 2).unwrap();
+    assert!(directory.join("tick_0000.png").exists());
+    assert!(directory.join("tick_0005.png").exists());
+    assert!(directory.join("occupancy.png").exists());
+
+    let gif = directory.join("seating.gif");
+    recording.write_gif(&gif, 2, 100).unwrap();
+    assert!(gif.exists());
+    assert!(directory.join("seating.occupancy.png").exists());
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+fn waiting_area_from(rows: &[&str]) -> WaitingArea {
+    let input: Vec<String> = rows.iter().map(|row| row.to_string()).collect();
+    WaitingArea::new(&input)
+}
+
+#[test]
+fn test_run_until_stabilized() {
+    let mut waiting_area = waiting_area_with_sample_input();
+    let mut ticks = 0;
+    let outcome = waiting_area.run(&SeatRule::adjacent(), |_| ticks += 1);
+
+    assert_eq!(outcome, SimulationOutcome::Stabilized { ticks: 5 });
+    assert_eq!(ticks, 5);
+    assert_eq!(waiting_area.get_number_of_occupied_seats(), 37);
+}
+
+#[test]
+fn test_run_until_cycle() {
+    // Two neighbours who can't stand each other keep sitting down and getting up together
+    let rule = SeatRule {
+        neighbour_strategy: NeighbourStrategy::Adjacent,
+        occupy_at_most: 0,
+        vacate_at_least: 1,
+    };
+    let mut waiting_area = waiting_area_from(&["LL"]);
+    assert_eq!(waiting_area.run(&rule, |_| {}), SimulationOutcome::Cycle { entry_tick: 0, period: 2 });
+
+    // The lone seat on the right fills up for good before the other two start flipping
+    let mut waiting_area = waiting_area_from(&["LL..L"]);
+    assert_eq!(waiting_area.run(&rule, |_| {}), SimulationOutcome::Cycle { entry_tick: 1, period: 2 });
+    assert_eq!(waiting_area.to_string(), "##..#\n");
+}
+
+#[test]
+fn test_toroidal_topology() {
+    let mut waiting_area = waiting_area_from(&["LLL", "LLL", "LLL"]);
+    waiting_area.set_topology(Topology::Toroidal);
+    assert_eq!(waiting_area.get_neighbour_graph_entry(0, 0, NeighbourStrategy::Adjacent).0.len(), 8);
+
+    // Every seat sees all eight others, so they all fill up and empty out together
+    assert_eq!(waiting_area.run(&SeatRule::adjacent(), |_| {}), SimulationOutcome::Cycle { entry_tick: 0, period: 2 });
+
+    // Looking left from the first seat wraps around to the last one
+    let mut waiting_area = waiting_area_from(&["L...#"]);
+    waiting_area.set_topology(Topology::Toroidal);
+    let (seats, _) = waiting_area.get_neighbour_graph_entry(0, 0, NeighbourStrategy::LineOfSight);
+    assert!(seats.contains(&4));
+
+    // A seat never sees itself, even with nothing but floor around it
+    let mut waiting_area = waiting_area_from(&["..", "L."]);
+    waiting_area.set_topology(Topology::Toroidal);
+    assert_eq!(waiting_area.get_neighbour_graph_entry(0, 1, NeighbourStrategy::LineOfSight), (vec![], 0));
+    assert!(waiting_area.tick(&SeatRule::line_of_sight()));
+    assert_eq!(waiting_area.get_number_of_occupied_seats(), 1);
+}
+
+#[test]
+fn test_walled_topology() {
+    let mut waiting_area = waiting_area_from(&["L"]);
+    assert!(waiting_area.tick(&SeatRule::adjacent()));
+    assert_eq!(waiting_area.get(0, 0), OccupiedSeat);
+
+    // With walls all around, the seat is never free enough to sit in
+    let mut waiting_area = waiting_area_from(&["L"]);
+    waiting_area.set_topology(Topology::Walled);
+    assert_eq!(waiting_area.get_neighbour_graph_entry(0, 0, NeighbourStrategy::Adjacent), (vec![], 8));
+    assert!(!waiting_area.tick(&SeatRule::adjacent()));
+
+    // Seats along the walls empty out first
+    let mut waiting_area = waiting_area_from(&["###", "#.#", "###"]);
+    waiting_area.set_topology(Topology::Walled);
+    assert!(waiting_area.tick(&SeatRule::adjacent()));
+    assert_eq!(waiting_area.to_string(), "LLL\nL.L\nLLL\n");
+}
@@ -1,7 +1,9 @@
-use std::collections::HashMap;
-use std::convert::TryInto;
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter};
+use std::ops::{Add, Sub, Mul};
+use core::fmt;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum MovementInstruction {
     North(usize),
     South(usize),
@@ -12,242 +14,337 @@ pub enum MovementInstruction {
     Forward(usize),
 }
 
+/// Why a single instruction couldn't be compiled.
+#[derive(Debug, PartialEq)]
+pub enum InstructionError {
+    Empty,
+    UnknownAction(char),
+    InvalidOperand(String),
+}
+
+impl Display for InstructionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            InstructionError::Empty => write!(f, "Empty instruction"),
+            InstructionError::UnknownAction(action) => write!(f, "Unknown movement instruction '{}'", action),
+            InstructionError::InvalidOperand(operand) => write!(f, "Invalid operand '{}'", operand),
+        }
+    }
+}
+
+/// An instruction that couldn't be compiled, and the (1-based) line it's on.
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub error: InstructionError,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Line {}: {}", self.line, self.error)
+    }
+}
+
 impl MovementInstruction {
-    pub fn compile(input: &str) -> MovementInstruction {
-        let instruction_char = input.chars().nth(0).unwrap();
-        let operand = &input[1..];
-        let operand = operand.parse::<usize>().unwrap();
+    pub fn compile(input: &str) -> Result<MovementInstruction, InstructionError> {
+        let mut chars = input.chars();
+        let instruction_char = chars.next().ok_or(InstructionError::Empty)?;
+        let operand = chars.as_str();
+        // Distances have to fit the i64 coordinates they're added to
+        let operand = operand.parse::<usize>().ok()
+            .filter(|operand| i64::try_from(*operand).is_ok())
+            .ok_or_else(|| InstructionError::InvalidOperand(String::from(operand)))?;
 
         match instruction_char {
-            'N' => MovementInstruction::North(operand),
-            'S' => MovementInstruction::South(operand),
-            'E' => MovementInstruction::East(operand),
-            'W' => MovementInstruction::West(operand),
-            'L' => MovementInstruction::Left(operand),
-            'R' => MovementInstruction::Right(operand),
-            'F' => MovementInstruction::Forward(operand),
-            _ => {
-                panic!("Unknown movement instruction")
-            }
+            'N' => Ok(MovementInstruction::North(operand)),
+            'S' => Ok(MovementInstruction::South(operand)),
+            'E' => Ok(MovementInstruction::East(operand)),
+            'W' => Ok(MovementInstruction::West(operand)),
+            'L' => Ok(MovementInstruction::Left(operand)),
+            'R' => Ok(MovementInstruction::Right(operand)),
+            'F' => Ok(MovementInstruction::Forward(operand)),
+            _ => Err(InstructionError::UnknownAction(instruction_char))
+        }
+    }
+
+    /// Moves the given distance in a compass direction; zero distances produce no instruction.
+    fn towards(direction: Direction, by: i64) -> Option<MovementInstruction> {
+        let by = by as usize;
+        match (by, direction) {
+            (0, _) => None,
+            (_, Direction::North) => Some(MovementInstruction::North(by)),
+            (_, Direction::South) => Some(MovementInstruction::South(by)),
+            (_, Direction::East) => Some(MovementInstruction::East(by)),
+            (_, Direction::West) => Some(MovementInstruction::West(by)),
+        }
+    }
+
+    /// The compass moves that add up to `offset`.
+    pub fn moves_by(offset: Vector) -> Vec<MovementInstruction> {
+        let north_south = if offset.north >= 0 { Direction::North } else { Direction::South };
+        let east_west = if offset.east >= 0 { Direction::East } else { Direction::West };
+
+        MovementInstruction::towards(north_south, offset.north.abs()).into_iter()
+            .chain(MovementInstruction::towards(east_west, offset.east.abs()))
+            .collect()
+    }
+}
+
+impl Display for MovementInstruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            MovementInstruction::North(by) => write!(f, "N{}", by),
+            MovementInstruction::South(by) => write!(f, "S{}", by),
+            MovementInstruction::East(by) => write!(f, "E{}", by),
+            MovementInstruction::West(by) => write!(f, "W{}", by),
+            MovementInstruction::Left(by) => write!(f, "L{}", by),
+            MovementInstruction::Right(by) => write!(f, "R{}", by),
+            MovementInstruction::Forward(by) => write!(f, "F{}", by),
         }
     }
 }
 
-#[derive(Debug, PartialEq, Hash)]
+/// Compiles one instruction per line, stopping at the first one that doesn't compile.
+pub fn parse_instructions(lines: &[String]) -> Result<Vec<MovementInstruction>, ParseError> {
+    lines.iter()
+        .enumerate()
+        .map(|(index, line)| {
+            MovementInstruction::compile(line.trim())
+                .map_err(|error| ParseError { line: index + 1, error })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Hash)]
 pub enum Direction { North, South, East, West }
 
+impl Direction {
+    pub fn unit_vector(self) -> Vector {
+        match self {
+            Direction::North => Vector { east: 0, north: 1 },
+            Direction::South => Vector { east: 0, north: -1 },
+            Direction::East => Vector { east: 1, north: 0 },
+            Direction::West => Vector { east: -1, north: 0 },
+        }
+    }
+
+    fn from_unit_vector(vector: Vector) -> Direction {
+        match (vector.east, vector.north) {
+            (0, 1) => Direction::North,
+            (0, -1) => Direction::South,
+            (1, 0) => Direction::East,
+            (-1, 0) => Direction::West,
+            _ => unreachable!("Rotating a unit vector by quarter turns always gives a unit vector")
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Hash)]
 pub enum RotationDirection { Left, Right }
 
-#[derive(Debug)]
-pub struct Waypoint {
-    pub north: f32,
-    pub east: f32,
+/// Returned when asked to turn by an angle that isn't a multiple of 90 degrees.
+#[derive(Debug, PartialEq)]
+pub struct InvalidRotation {
+    pub degrees: usize,
 }
 
-#[derive(Debug)]
-pub struct Ship {
-    pub direction: Direction,
-    pub north: f32,
-    pub east: f32,
-    pub waypoint: Waypoint,
+impl Display for InvalidRotation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Can only turn in multiples of 90 degrees, not {}", self.degrees)
+    }
 }
 
-impl Ship {
-    pub fn new() -> Ship {
-        Ship { direction: Direction::East, north: 0.0, east: 0.0, waypoint: Waypoint { east: 10.0, north: 1.0 } }
-    }
-
-    pub fn rotate(facing: &Direction, rotation_direction: RotationDirection, by: usize) -> Direction {
-        // These are the only valid values in my input
-        assert!(by == 90 || by == 180 || by == 270);
-
-        if rotation_direction == RotationDirection::Left {
-            match facing {
-                Direction::North => {
-                    if by == 90 {
-                        return Direction::West;
-                    } else if by == 180 {
-                        return Direction::South;
-                    } else if by == 270 {
-                        return Direction::East;
-                    }
-                }
-                Direction::South => {
-                    if by == 90 {
-                        return Direction::East;
-                    } else if by == 180 {
-                        return Direction::North;
-                    } else if by == 270 {
-                        return Direction::West;
-                    }
-                }
-                Direction::East => {
-                    if by == 90 {
-                        return Direction::North;
-                    } else if by == 180 {
-                        return Direction::West;
-                    } else if by == 270 {
-                        return Direction::South;
-                    }
-                }
-                Direction::West => {
-                    if by == 90 {
-                        return Direction::South;
-                    } else if by == 180 {
-                        return Direction::East;
-                    } else if by == 270 {
-                        return Direction::North;
-                    }
-                }
-            }
-        } else if rotation_direction == RotationDirection::Right {
-            match facing {
-                Direction::North => {
-                    if by == 90 {
-                        return Direction::East;
-                    } else if by == 180 {
-                        return Direction::South;
-                    } else if by == 270 {
-                        return Direction::West;
-                    }
-                }
-                Direction::South => {
-                    if by == 90 {
-                        return Direction::West;
-                    } else if by == 180 {
-                        return Direction::North;
-                    } else if by == 270 {
-                        return Direction::East;
-                    }
-                }
-                Direction::East => {
-                    if by == 90 {
-                        return Direction::South;
-                    } else if by == 180 {
-                        return Direction::West;
-                    } else if by == 270 {
-                        return Direction::North;
-                    }
-                }
-                Direction::West => {
-                    if by == 90 {
-                        return Direction::North;
-                    } else if by == 180 {
-                        return Direction::East;
-                    } else if by == 270 {
-                        return Direction::South;
-                    }
-                }
-            }
-        }
+/// Why the ship couldn't follow an instruction.
+#[derive(Debug, PartialEq)]
+pub enum MovementError {
+    InvalidRotation(InvalidRotation),
+    /// The ship or waypoint would end up further out than an i64 coordinate can go.
+    OutOfRange,
+}
 
-        panic!("Unable to compute new direction");
+impl From<InvalidRotation> for MovementError {
+    fn from(error: InvalidRotation) -> MovementError {
+        MovementError::InvalidRotation(error)
     }
+}
 
-    pub fn rotate_waypoint(waypoint: &Waypoint, rotation_direction: RotationDirection, by: usize) -> Waypoint {
-        let mut by = by as f32;
+impl Display for MovementError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            MovementError::InvalidRotation(error) => write!(f, "{}", error),
+            MovementError::OutOfRange => write!(f, "Moving that far takes the ship off the chart"),
+        }
+    }
+}
+
+/// A position or displacement on the grid, in whole units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Vector {
+    pub east: i64,
+    pub north: i64,
+}
 
-        // Angles are measured anti-clockwise as a standard. So a 90 degree Right rotation is a -90 degree anti-clockwise rotation
+impl Vector {
+    /// Rotates the vector about the origin by `by` degrees. Only quarter turns keep us on the
+    /// grid, so those are the only ones allowed.
+    pub fn rotate(self, rotation_direction: RotationDirection, by: usize) -> Result<Vector, InvalidRotation> {
+        if !by.is_multiple_of(90) {
+            return Err(InvalidRotation { degrees: by });
+        }
+
+        // Angles are measured anti-clockwise as a standard. So a 90 degree Right rotation is a 270 degree anti-clockwise rotation
+        let mut quarter_turns = (by / 90) % 4;
         if rotation_direction == RotationDirection::Right {
-            by = 360.0 - by;
+            quarter_turns = (4 - quarter_turns) % 4;
+        }
+
+        let mut vector = self;
+        for _ in 0..quarter_turns {
+            vector = Vector { east: -vector.north, north: vector.east };
         }
-        by = by.to_radians();
 
-        let sin_t = (by as f32).sin();
-        let cos_t = (by as f32).cos();
-        let east = waypoint.east as f32;
-        let north = waypoint.north as f32;
+        Ok(vector)
+    }
 
-        let new_east = cos_t * east - sin_t * north;
-        let new_north = sin_t * east + cos_t * north;
+    pub fn manhattan_length(self) -> i64 {
+        self.east.abs() + self.north.abs()
+    }
 
-        Waypoint {
-            east: new_east,
-            north: new_north
-        }
+    pub fn checked_add(self, other: Vector) -> Option<Vector> {
+        Some(Vector { east: self.east.checked_add(other.east)?, north: self.north.checked_add(other.north)? })
     }
 
-    pub fn move_waypoint(waypoint: &Waypoint, direction: Direction, by: usize) -> Waypoint {
-        match direction {
-            Direction::North => Waypoint {north: waypoint.north + by as f32, ..*waypoint},
-            Direction::South => Waypoint {north: waypoint.north - by as f32, ..*waypoint},
-            Direction::East => Waypoint {east: waypoint.east + by as f32, ..*waypoint},
-            Direction::West => Waypoint {east: waypoint.east - by as f32, ..*waypoint},
-        }
+    pub fn checked_mul(self, by: i64) -> Option<Vector> {
+        Some(Vector { east: self.east.checked_mul(by)?, north: self.north.checked_mul(by)? })
+    }
+}
+
+impl Add for Vector {
+    type Output = Vector;
+
+    fn add(self, other: Vector) -> Vector {
+        Vector { east: self.east + other.east, north: self.north + other.north }
+    }
+}
+
+impl Sub for Vector {
+    type Output = Vector;
+
+    fn sub(self, other: Vector) -> Vector {
+        Vector { east: self.east - other.east, north: self.north - other.north }
+    }
+}
+
+impl Mul<i64> for Vector {
+    type Output = Vector;
+
+    fn mul(self, by: i64) -> Vector {
+        Vector { east: self.east * by, north: self.north * by }
     }
+}
 
-    pub fn move_ship(&mut self, movement: &MovementInstruction) {
+/// The waypoint is always relative to the ship.
+pub type Waypoint = Vector;
+
+#[derive(Debug)]
+pub struct Ship {
+    pub direction: Direction,
+    pub position: Vector,
+    pub waypoint: Waypoint,
+}
+
+impl Default for Ship {
+    fn default() -> Ship {
+        Ship::new()
+    }
+}
+
+impl Ship {
+    pub fn new() -> Ship {
+        Ship { direction: Direction::East, position: Vector::default(), waypoint: Waypoint { east: 10, north: 1 } }
+    }
+
+    pub fn rotate(facing: &Direction, rotation_direction: RotationDirection, by: usize) -> Result<Direction, InvalidRotation> {
+        let facing = facing.unit_vector().rotate(rotation_direction, by)?;
+        Ok(Direction::from_unit_vector(facing))
+    }
+
+    pub fn rotate_waypoint(waypoint: &Waypoint, rotation_direction: RotationDirection, by: usize) -> Result<Waypoint, InvalidRotation> {
+        waypoint.rotate(rotation_direction, by)
+    }
+
+    pub fn move_waypoint(waypoint: &Waypoint, direction: Direction, by: usize) -> Result<Waypoint, MovementError> {
+        i64::try_from(by).ok()
+            .and_then(|by| direction.unit_vector().checked_mul(by))
+            .and_then(|offset| waypoint.checked_add(offset))
+            .ok_or(MovementError::OutOfRange)
+    }
+
+    pub fn move_ship(&mut self, movement: &MovementInstruction) -> Result<(), MovementError> {
         match movement {
             MovementInstruction::North(by) => {
-                self.north += *by as f32;
+                self.position = Ship::move_waypoint(&self.position, Direction::North, *by)?;
             }
             MovementInstruction::South(by) => {
-                self.north -= *by as f32;
+                self.position = Ship::move_waypoint(&self.position, Direction::South, *by)?;
             }
             MovementInstruction::East(by) => {
-                self.east += *by as f32;
+                self.position = Ship::move_waypoint(&self.position, Direction::East, *by)?;
             }
             MovementInstruction::West(by) => {
-                self.east -= *by as f32;
+                self.position = Ship::move_waypoint(&self.position, Direction::West, *by)?;
             }
             MovementInstruction::Left(by) => {
-                self.direction = Ship::rotate(&self.direction, RotationDirection::Left, *by)
+                self.direction = Ship::rotate(&self.direction, RotationDirection::Left, *by)?;
             }
             MovementInstruction::Right(by) => {
-                self.direction = Ship::rotate(&self.direction, RotationDirection::Right, *by)
+                self.direction = Ship::rotate(&self.direction, RotationDirection::Right, *by)?;
             }
             MovementInstruction::Forward(by) => {
-                match self.direction {
-                    Direction::North => {
-                        self.north += *by as f32;
-                    }
-                    Direction::South => {
-                        self.north -= *by as f32;
-                    }
-                    Direction::East => {
-                        self.east += *by as f32;
-                    }
-                    Direction::West => {
-                        self.east -= *by as f32;
-                    }
-                }
+                self.position = Ship::move_waypoint(&self.position, self.direction, *by)?;
             }
         }
+
+        Ok(())
     }
 
-    pub fn follow_instruction(&mut self, instruction: &MovementInstruction) {
+    pub fn follow_instruction(&mut self, instruction: &MovementInstruction) -> Result<(), MovementError> {
         match instruction {
             MovementInstruction::North(by) => {
-                self.waypoint = Ship::move_waypoint(&self.waypoint, Direction::North, *by);
+                self.waypoint = Ship::move_waypoint(&self.waypoint, Direction::North, *by)?;
             }
             MovementInstruction::South(by) => {
-                self.waypoint = Ship::move_waypoint(&self.waypoint, Direction::South, *by);
+                self.waypoint = Ship::move_waypoint(&self.waypoint, Direction::South, *by)?;
             }
             MovementInstruction::East(by) => {
-                self.waypoint = Ship::move_waypoint(&self.waypoint, Direction::East, *by);
+                self.waypoint = Ship::move_waypoint(&self.waypoint, Direction::East, *by)?;
             }
             MovementInstruction::West(by) => {
-                self.waypoint = Ship::move_waypoint(&self.waypoint, Direction::West, *by);
+                self.waypoint = Ship::move_waypoint(&self.waypoint, Direction::West, *by)?;
             }
             MovementInstruction::Left(by) => {
-                self.waypoint = Ship::rotate_waypoint(&self.waypoint, RotationDirection::Left, *by);
+                self.waypoint = Ship::rotate_waypoint(&self.waypoint, RotationDirection::Left, *by)?;
             }
             MovementInstruction::Right(by) => {
-                self.waypoint = Ship::rotate_waypoint(&self.waypoint, RotationDirection::Right, *by);
+                self.waypoint = Ship::rotate_waypoint(&self.waypoint, RotationDirection::Right, *by)?;
             }
             MovementInstruction::Forward(by) => {
-                self.move_ship_towards_waypoint(*by);
+                self.move_ship_towards_waypoint(*by)?;
             }
         }
+
+        Ok(())
     }
 
-    pub fn move_ship_towards_waypoint(&mut self, by: usize) {
-        self.east += self.waypoint.east * by as f32;
-        self.north += self.waypoint.north * by as f32;
+    pub fn move_ship_towards_waypoint(&mut self, by: usize) -> Result<(), MovementError> {
+        self.position = i64::try_from(by).ok()
+            .and_then(|by| self.waypoint.checked_mul(by))
+            .and_then(|offset| self.position.checked_add(offset))
+            .ok_or(MovementError::OutOfRange)?;
+        Ok(())
     }
 
-    pub fn get_manhattan_distance(&self) -> f32 {
-        self.north.round().abs() + self.east.round().abs()
+    pub fn get_manhattan_distance(&self) -> i64 {
+        self.position.manhattan_length()
     }
-}
\ No newline at end of file
+}
@@ -1,11 +1,21 @@
 use std::path::Path;
 use std::fs::File;
 use std::io::{BufReader, BufRead};
-use crate::ferry_simulator::{Ship, MovementInstruction};
+use std::path::PathBuf;
+use std::env;
+use std::process;
+use crate::ferry_simulator::parse_instructions;
+use crate::navigation::{NavigationMode, HeadingNavigation, WaypointNavigation, Route};
 
 mod ferry_simulator;
+mod navigation;
+mod route_export;
+#[cfg(test)]
 mod tests;
 
+const USAGE: &str = "Usage: day12 [--mode heading|waypoint] [--svg FILE] [--png FILE] [--return-home] [input file]";
+const EXPORT_SIZE: u32 = 1000;
+
 fn get_input(filename: &str) -> Vec<String> {
     let path = Path::new(filename);
     let file = File::open(path).unwrap();
@@ -19,14 +29,86 @@ fn get_input(filename: &str) -> Vec<String> {
     input
 }
 
+/// Inserts the navigation mode into the file name, so `route.svg` becomes `route.waypoint.svg`.
+fn path_for_mode(path: &str, mode: &dyn NavigationMode) -> PathBuf {
+    let path = Path::new(path);
+    let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or("");
+    path.with_extension(format!("{}.{}", mode.name(), extension))
+}
+
 fn main() {
-    let input = get_input("input.txt");
-    //59816
-    let mut ship = Ship::new();
-    for instruction in input.iter() {
-        let movement_instruction = MovementInstruction::compile(&instruction[..]);
-        ship.follow_instruction(&movement_instruction);
+    let mut filename = String::from("input.txt");
+    let mut modes: Vec<Box<dyn NavigationMode>> = vec![Box::new(HeadingNavigation), Box::new(WaypointNavigation)];
+    let mut svg_path = None;
+    let mut png_path = None;
+    let mut return_home = false;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match &arg[..] {
+            "--mode" => match args.next().as_deref() {
+                Some("heading") => modes = vec![Box::new(HeadingNavigation)],
+                Some("waypoint") => modes = vec![Box::new(WaypointNavigation)],
+                _ => {
+                    eprintln!("--mode expects heading or waypoint\n{}", USAGE);
+                    process::exit(1);
+                }
+            },
+            "--svg" => svg_path = args.next(),
+            "--png" => png_path = args.next(),
+            "--return-home" => return_home = true,
+            _ => filename = arg,
+        }
     }
 
-    println!("Manhattan distance: {}", ship.get_manhattan_distance());
+    let input = get_input(&filename);
+    let instructions = match parse_instructions(&input) {
+        Ok(instructions) => instructions,
+        Err(error) => {
+            println!("{}", error);
+            process::exit(1);
+        }
+    };
+
+    for mode in modes.iter() {
+        let route = match Route::navigate(mode.as_ref(), &instructions) {
+            Ok(route) => route,
+            Err(error) => {
+                println!("{}", error);
+                process::exit(1);
+            }
+        };
+
+        let extent = route.extent();
+        println!("Navigating by {}:", mode.name());
+        println!("  Manhattan distance: {}", route.ship.get_manhattan_distance());
+        println!("  Distance travelled: {}", route.total_distance());
+        println!("  Farthest distance from the start: {}", route.farthest_distance());
+        println!(
+            "  Extent: east {} to {}, north {} to {}",
+            extent.min.east, extent.max.east, extent.min.north, extent.max.north
+        );
+
+        if return_home {
+            let instructions: Vec<String> = mode.plan_return_home(&route.ship).iter()
+                .map(|instruction| instruction.to_string())
+                .collect();
+            println!("  Return home with: {}", instructions.join(" "));
+        }
+
+        if let Some(svg_path) = &svg_path {
+            let result = route_export::write_svg(&route, &path_for_mode(svg_path, mode.as_ref()), EXPORT_SIZE);
+            if let Err(error) = result {
+                eprintln!("Export failed: {}", error);
+                process::exit(1);
+            }
+        }
+        if let Some(png_path) = &png_path {
+            let result = route_export::write_png(&route, &path_for_mode(png_path, mode.as_ref()), EXPORT_SIZE);
+            if let Err(error) = result {
+                eprintln!("Export failed: {}", error);
+                process::exit(1);
+            }
+        }
+    }
 }
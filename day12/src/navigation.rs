@@ -0,0 +1,137 @@
+use crate::ferry_simulator::{Ship, MovementInstruction, MovementError, Vector};
+use std::fmt::{Display, Formatter};
+use core::fmt;
+
+/// A way of interpreting the navigation instructions.
+pub trait NavigationMode {
+    fn name(&self) -> &'static str;
+
+    fn apply(&self, ship: &mut Ship, instruction: &MovementInstruction) -> Result<(), MovementError>;
+
+    /// The vector the ship moves along for each unit of a forward instruction.
+    fn steering_vector(&self, ship: &Ship) -> Vector;
+
+    /// Instructions that take the ship from wherever it is back to the start.
+    fn plan_return_home(&self, ship: &Ship) -> Vec<MovementInstruction>;
+}
+
+/// Part 1: N/S/E/W move the ship, L/R turn it and F moves it the way it's facing.
+pub struct HeadingNavigation;
+
+impl NavigationMode for HeadingNavigation {
+    fn name(&self) -> &'static str {
+        "heading"
+    }
+
+    fn apply(&self, ship: &mut Ship, instruction: &MovementInstruction) -> Result<(), MovementError> {
+        ship.move_ship(instruction)
+    }
+
+    fn steering_vector(&self, ship: &Ship) -> Vector {
+        ship.direction.unit_vector()
+    }
+
+    fn plan_return_home(&self, ship: &Ship) -> Vec<MovementInstruction> {
+        MovementInstruction::moves_by(Vector::default() - ship.position)
+    }
+}
+
+/// Part 2: N/S/E/W move the waypoint, L/R rotate it around the ship and F moves the ship
+/// towards it.
+pub struct WaypointNavigation;
+
+impl NavigationMode for WaypointNavigation {
+    fn name(&self) -> &'static str {
+        "waypoint"
+    }
+
+    fn apply(&self, ship: &mut Ship, instruction: &MovementInstruction) -> Result<(), MovementError> {
+        ship.follow_instruction(instruction)
+    }
+
+    fn steering_vector(&self, ship: &Ship) -> Vector {
+        ship.waypoint
+    }
+
+    /// Only forward instructions move the ship, so we move the waypoint to exactly where the
+    /// ship needs to go and then take a single step towards it.
+    fn plan_return_home(&self, ship: &Ship) -> Vec<MovementInstruction> {
+        if ship.position == Vector::default() {
+            return vec![];
+        }
+
+        let mut instructions = MovementInstruction::moves_by(Vector::default() - ship.position - ship.waypoint);
+        instructions.push(MovementInstruction::Forward(1));
+        instructions
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct RouteError {
+    pub line: usize,
+    pub cause: MovementError,
+}
+
+impl Display for RouteError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Line {}: {}", self.line, self.cause)
+    }
+}
+
+/// The bounding box of every position on a route.
+#[derive(Debug, PartialEq)]
+pub struct Extent {
+    pub min: Vector,
+    pub max: Vector,
+}
+
+/// Where the ship was, and which way it was steering, at the start and after every instruction.
+#[derive(Debug)]
+pub struct Route {
+    pub positions: Vec<Vector>,
+    pub steering_vectors: Vec<Vector>,
+    pub ship: Ship,
+}
+
+impl Route {
+    /// Sails a new ship through the instructions.
+    pub fn navigate(mode: &dyn NavigationMode, instructions: &[MovementInstruction]) -> Result<Route, RouteError> {
+        let mut ship = Ship::new();
+        let mut positions = vec![ship.position];
+        let mut steering_vectors = vec![mode.steering_vector(&ship)];
+
+        for (index, instruction) in instructions.iter().enumerate() {
+            mode.apply(&mut ship, instruction)
+                .map_err(|cause| RouteError { line: index + 1, cause })?;
+
+            positions.push(ship.position);
+            steering_vectors.push(mode.steering_vector(&ship));
+        }
+
+        Ok(Route { positions, steering_vectors, ship })
+    }
+
+    /// The Manhattan distance covered over the whole route, rather than just start to finish.
+    pub fn total_distance(&self) -> i64 {
+        self.positions.windows(2)
+            .map(|leg| (leg[1] - leg[0]).manhattan_length())
+            .sum()
+    }
+
+    pub fn extent(&self) -> Extent {
+        let mut extent = Extent { min: self.positions[0], max: self.positions[0] };
+        for position in self.positions.iter() {
+            extent.min.east = extent.min.east.min(position.east);
+            extent.min.north = extent.min.north.min(position.north);
+            extent.max.east = extent.max.east.max(position.east);
+            extent.max.north = extent.max.north.max(position.north);
+        }
+
+        extent
+    }
+
+    /// The largest Manhattan distance from the start the ship ever reached.
+    pub fn farthest_distance(&self) -> i64 {
+        self.positions.iter().map(|position| position.manhattan_length()).max().unwrap_or(0)
+    }
+}
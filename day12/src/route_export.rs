@@ -0,0 +1,168 @@
+use crate::ferry_simulator::Vector;
+use crate::navigation::Route;
+use image::{Rgb, RgbImage, ImageResult};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+const MARGIN: f64 = 10.0;
+const MARKER_RADIUS: i64 = 3;
+
+const BACKGROUND_COLOR: Rgb<u8> = Rgb([255, 255, 255]);
+const ROUTE_COLOR: Rgb<u8> = Rgb([32, 96, 192]);
+const STEERING_COLOR: Rgb<u8> = Rgb([224, 128, 32]);
+const START_COLOR: Rgb<u8> = Rgb([32, 160, 32]);
+const END_COLOR: Rgb<u8> = Rgb([200, 32, 32]);
+
+fn to_hex(color: Rgb<u8>) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+/// Maps sea positions onto image pixels, with north pointing up and the whole route, steering
+/// vectors included, fitting in a `size` x `size` square.
+struct Projection {
+    min_east: i64,
+    max_north: i64,
+    scale: f64,
+    width: u32,
+    height: u32,
+}
+
+impl Projection {
+    fn fit(route: &Route, size: u32) -> Projection {
+        let steering_targets = route.positions.iter()
+            .zip(route.steering_vectors.iter())
+            .map(|(position, steering_vector)| *position + *steering_vector);
+        let points: Vec<Vector> = route.positions.iter().copied().chain(steering_targets).collect();
+
+        let min_east = points.iter().map(|point| point.east).min().unwrap_or(0);
+        let max_east = points.iter().map(|point| point.east).max().unwrap_or(0);
+        let min_north = points.iter().map(|point| point.north).min().unwrap_or(0);
+        let max_north = points.iter().map(|point| point.north).max().unwrap_or(0);
+
+        let span = (max_east - min_east).max(max_north - min_north).max(1);
+        let scale = (size as f64 - 2.0 * MARGIN).max(1.0) / span as f64;
+
+        Projection {
+            min_east,
+            max_north,
+            scale,
+            width: ((max_east - min_east) as f64 * scale + 2.0 * MARGIN).ceil() as u32 + 1,
+            height: ((max_north - min_north) as f64 * scale + 2.0 * MARGIN).ceil() as u32 + 1,
+        }
+    }
+
+    fn project(&self, point: Vector) -> (f64, f64) {
+        (
+            MARGIN + (point.east - self.min_east) as f64 * self.scale,
+            MARGIN + (self.max_north - point.north) as f64 * self.scale,
+        )
+    }
+}
+
+/// Draws the route as a polyline, the steering vector (waypoint or heading) at every stop as a
+/// short line, and the start and end of the route as dots.
+pub fn route_to_svg(route: &Route, size: u32) -> String {
+    let projection = Projection::fit(route, size);
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">\n",
+        projection.width, projection.height
+    );
+    svg.push_str(&format!("  <rect width=\"100%\" height=\"100%\" fill=\"{}\"/>\n", to_hex(BACKGROUND_COLOR)));
+
+    svg.push_str(&format!("  <g stroke=\"{}\" stroke-width=\"0.5\" opacity=\"0.6\">\n", to_hex(STEERING_COLOR)));
+    for (position, steering_vector) in route.positions.iter().zip(route.steering_vectors.iter()) {
+        let (x1, y1) = projection.project(*position);
+        let (x2, y2) = projection.project(*position + *steering_vector);
+        svg.push_str(&format!("    <line x1=\"{:.2}\" y1=\"{:.2}\" x2=\"{:.2}\" y2=\"{:.2}\"/>\n", x1, y1, x2, y2));
+    }
+    svg.push_str("  </g>\n");
+
+    let points: Vec<String> = route.positions.iter()
+        .map(|position| projection.project(*position))
+        .map(|(x, y)| format!("{:.2},{:.2}", x, y))
+        .collect();
+    svg.push_str(&format!(
+        "  <polyline fill=\"none\" stroke=\"{}\" stroke-width=\"1\" points=\"{}\"/>\n",
+        to_hex(ROUTE_COLOR), points.join(" ")
+    ));
+
+    let start = projection.project(route.positions[0]);
+    let end = projection.project(*route.positions.last().unwrap());
+    for ((x, y), color) in [(start, START_COLOR), (end, END_COLOR)].iter() {
+        svg.push_str(&format!(
+            "  <circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"{}\" fill=\"{}\"/>\n",
+            x, y, MARKER_RADIUS, to_hex(*color)
+        ));
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+pub fn write_svg(route: &Route, path: &Path, size: u32) -> io::Result<()> {
+    fs::write(path, route_to_svg(route, size))
+}
+
+fn draw_line(image: &mut RgbImage, from: (f64, f64), to: (f64, f64), color: Rgb<u8>) {
+    let (mut x, mut y) = (from.0.round() as i64, from.1.round() as i64);
+    let (x_end, y_end) = (to.0.round() as i64, to.1.round() as i64);
+
+    // Bresenham's line algorithm
+    let dx = (x_end - x).abs();
+    let dy = -(y_end - y).abs();
+    let step_x = if x < x_end { 1 } else { -1 };
+    let step_y = if y < y_end { 1 } else { -1 };
+    let mut error = dx + dy;
+
+    loop {
+        if x >= 0 && y >= 0 && (x as u32) < image.width() && (y as u32) < image.height() {
+            image.put_pixel(x as u32, y as u32, color);
+        }
+        if x == x_end && y == y_end {
+            break;
+        }
+
+        let doubled_error = 2 * error;
+        if doubled_error >= dy {
+            error += dy;
+            x += step_x;
+        }
+        if doubled_error <= dx {
+            error += dx;
+            y += step_y;
+        }
+    }
+}
+
+fn draw_marker(image: &mut RgbImage, (x, y): (f64, f64), color: Rgb<u8>) {
+    let (x, y) = (x.round() as i64, y.round() as i64);
+    for dy in -MARKER_RADIUS..=MARKER_RADIUS {
+        draw_line(image, ((x - MARKER_RADIUS) as f64, (y + dy) as f64), ((x + MARKER_RADIUS) as f64, (y + dy) as f64), color);
+    }
+}
+
+/// Draws the same picture as `route_to_svg`, as a bitmap.
+pub fn render_png(route: &Route, size: u32) -> RgbImage {
+    let projection = Projection::fit(route, size);
+    let mut image = RgbImage::from_pixel(projection.width, projection.height, BACKGROUND_COLOR);
+
+    for (position, steering_vector) in route.positions.iter().zip(route.steering_vectors.iter()) {
+        let from = projection.project(*position);
+        let to = projection.project(*position + *steering_vector);
+        draw_line(&mut image, from, to, STEERING_COLOR);
+    }
+
+    for leg in route.positions.windows(2) {
+        draw_line(&mut image, projection.project(leg[0]), projection.project(leg[1]), ROUTE_COLOR);
+    }
+
+    draw_marker(&mut image, projection.project(route.positions[0]), START_COLOR);
+    draw_marker(&mut image, projection.project(*route.positions.last().unwrap()), END_COLOR);
+
+    image
+}
+
+pub fn write_png(route: &Route, path: &Path, size: u32) -> ImageResult<()> {
+    render_png(route, size).save(path)
+}
@@ -1,66 +1,68 @@
 use crate::ferry_simulator::*;
+use crate::navigation::{Route, RouteError, Extent, NavigationMode, HeadingNavigation, WaypointNavigation};
+use crate::route_export::{route_to_svg, render_png};
 use crate::ferry_simulator::Direction::{North, West, South, East};
 use crate::ferry_simulator::RotationDirection::{Left, Right};
 
 #[test]
 fn test_rotation() {
-    assert_eq!(Ship::rotate(&North, Left, 90), West, "North to Left 90");
-    assert_eq!(Ship::rotate(&North, Left, 180), South, "North to Left 180");
-    assert_eq!(Ship::rotate(&North, Left, 270), East, "North to Left 270");
+    assert_eq!(Ship::rotate(&North, Left, 90).unwrap(), West, "North to Left 90");
+    assert_eq!(Ship::rotate(&North, Left, 180).unwrap(), South, "North to Left 180");
+    assert_eq!(Ship::rotate(&North, Left, 270).unwrap(), East, "North to Left 270");
 
-    assert_eq!(Ship::rotate(&North, Right, 90), East, "North to Right 90");
-    assert_eq!(Ship::rotate(&North, Right, 180), South, "North to Right 180");
-    assert_eq!(Ship::rotate(&North, Right, 270), West, "North to Right 270");
+    assert_eq!(Ship::rotate(&North, Right, 90).unwrap(), East, "North to Right 90");
+    assert_eq!(Ship::rotate(&North, Right, 180).unwrap(), South, "North to Right 180");
+    assert_eq!(Ship::rotate(&North, Right, 270).unwrap(), West, "North to Right 270");
 
-    assert_eq!(Ship::rotate(&East, Left, 90), North, "East to Left 90");
-    assert_eq!(Ship::rotate(&East, Left, 180), West, "East to Left 180");
-    assert_eq!(Ship::rotate(&East, Left, 270), South, "East to Left 270");
+    assert_eq!(Ship::rotate(&East, Left, 90).unwrap(), North, "East to Left 90");
+    assert_eq!(Ship::rotate(&East, Left, 180).unwrap(), West, "East to Left 180");
+    assert_eq!(Ship::rotate(&East, Left, 270).unwrap(), South, "East to Left 270");
 
-    assert_eq!(Ship::rotate(&East, Right, 90), South, "East to Right 90");
-    assert_eq!(Ship::rotate(&East, Right, 180), West, "East to Right 180");
-    assert_eq!(Ship::rotate(&East, Right, 270), North, "East to Right 270");
+    assert_eq!(Ship::rotate(&East, Right, 90).unwrap(), South, "East to Right 90");
+    assert_eq!(Ship::rotate(&East, Right, 180).unwrap(), West, "East to Right 180");
+    assert_eq!(Ship::rotate(&East, Right, 270).unwrap(), North, "East to Right 270");
 
-    assert_eq!(Ship::rotate(&South, Left, 90), East, "South to Left 90");
-    assert_eq!(Ship::rotate(&South, Left, 180), North, "South to Left 180");
-    assert_eq!(Ship::rotate(&South, Left, 270), West, "South to Left 270");
+    assert_eq!(Ship::rotate(&South, Left, 90).unwrap(), East, "South to Left 90");
+    assert_eq!(Ship::rotate(&South, Left, 180).unwrap(), North, "South to Left 180");
+    assert_eq!(Ship::rotate(&South, Left, 270).unwrap(), West, "South to Left 270");
 
-    assert_eq!(Ship::rotate(&South, Right, 90), West, "South to Right 90");
-    assert_eq!(Ship::rotate(&South, Right, 180), North, "South to Right 180");
-    assert_eq!(Ship::rotate(&South, Right, 270), East, "South to Right 270");
+    assert_eq!(Ship::rotate(&South, Right, 90).unwrap(), West, "South to Right 90");
+    assert_eq!(Ship::rotate(&South, Right, 180).unwrap(), North, "South to Right 180");
+    assert_eq!(Ship::rotate(&South, Right, 270).unwrap(), East, "South to Right 270");
 
-    assert_eq!(Ship::rotate(&West, Left, 90), South, "West to Left 90");
-    assert_eq!(Ship::rotate(&West, Left, 180), East, "West to Left 180");
-    assert_eq!(Ship::rotate(&West, Left, 270), North, "West to Left 270");
+    assert_eq!(Ship::rotate(&West, Left, 90).unwrap(), South, "West to Left 90");
+    assert_eq!(Ship::rotate(&West, Left, 180).unwrap(), East, "West to Left 180");
+    assert_eq!(Ship::rotate(&West, Left, 270).unwrap(), North, "West to Left 270");
 
-    assert_eq!(Ship::rotate(&West, Right, 90), North, "West to Right 90");
-    assert_eq!(Ship::rotate(&West, Right, 180), East, "West to Right 180");
-    assert_eq!(Ship::rotate(&West, Right, 270), South, "West to Right 270");
+    assert_eq!(Ship::rotate(&West, Right, 90).unwrap(), North, "West to Right 90");
+    assert_eq!(Ship::rotate(&West, Right, 180).unwrap(), East, "West to Right 180");
+    assert_eq!(Ship::rotate(&West, Right, 270).unwrap(), South, "West to Right 270");
 }
 
 #[test]
 fn test_move_ship() {
     let mut ship = Ship::new();
 
-    ship.move_ship(&MovementInstruction::compile("F10"));
-    assert_eq!(ship.east, 10.0);
-    assert_eq!(ship.north, 0.0);
+    ship.move_ship(&MovementInstruction::compile("F10").unwrap()).unwrap();
+    assert_eq!(ship.position.east, 10);
+    assert_eq!(ship.position.north, 0);
 
-    ship.move_ship(&MovementInstruction::compile("N3"));
-    assert_eq!(ship.east, 10.0);
-    assert_eq!(ship.north, 3.0);
+    ship.move_ship(&MovementInstruction::compile("N3").unwrap()).unwrap();
+    assert_eq!(ship.position.east, 10);
+    assert_eq!(ship.position.north, 3);
 
-    ship.move_ship(&MovementInstruction::compile("F7"));
-    assert_eq!(ship.east, 17.0);
-    assert_eq!(ship.north, 3.0);
+    ship.move_ship(&MovementInstruction::compile("F7").unwrap()).unwrap();
+    assert_eq!(ship.position.east, 17);
+    assert_eq!(ship.position.north, 3);
 
-    ship.move_ship(&MovementInstruction::compile("R90"));
+    ship.move_ship(&MovementInstruction::compile("R90").unwrap()).unwrap();
     assert_eq!(ship.direction, South);
-    assert_eq!(ship.east, 17.0);
-    assert_eq!(ship.north, 3.0);
+    assert_eq!(ship.position.east, 17);
+    assert_eq!(ship.position.north, 3);
 
-    ship.move_ship(&MovementInstruction::compile("F11"));
-    assert_eq!(ship.east, 17.0);
-    assert_eq!(ship.north, -8.0);
+    ship.move_ship(&MovementInstruction::compile("F11").unwrap()).unwrap();
+    assert_eq!(ship.position.east, 17);
+    assert_eq!(ship.position.north, -8);
 }
 
 #[test]
@@ -76,46 +78,46 @@ fn test_get_manhattan_distance() {
     let mut ship = Ship::new();
 
     for instruction in input.iter() {
-        ship.move_ship(&MovementInstruction::compile(*instruction));
+        ship.move_ship(&MovementInstruction::compile(instruction).unwrap()).unwrap();
     }
 
-    assert_eq!(25.0, ship.get_manhattan_distance());
+    assert_eq!(25, ship.get_manhattan_distance());
 }
 
 #[test]
 fn test_rotate_waypoint() {
     let waypoint = Ship::rotate_waypoint(
-        &Waypoint { east: 10.0, north: 4.0 },
+        &Waypoint { east: 10, north: 4 },
         Right,
         90,
-    );
+    ).unwrap();
 
-    assert_eq!(waypoint.east.round(), 4.0);
-    assert_eq!(waypoint.north.round(), -10.0);
+    assert_eq!(waypoint.east, 4);
+    assert_eq!(waypoint.north, -10);
 
     let waypoint = Ship::rotate_waypoint(
         &waypoint,
         Left,
         90
-    );
-    assert_eq!(waypoint.east.round(), 10.0);
-    assert_eq!(waypoint.north.round(), 4.0);
+    ).unwrap();
+    assert_eq!(waypoint.east, 10);
+    assert_eq!(waypoint.north, 4);
 }
 
 #[test]
 fn test_move_waypoint() {
-    let waypoint = Waypoint { east:10.0, north: 1.0};
-    let waypoint = Ship::move_waypoint(&waypoint, North, 3);
-    assert_eq!(waypoint.east, 10.0);
-    assert_eq!(waypoint.north, 4.0);
+    let waypoint = Waypoint { east: 10, north: 1 };
+    let waypoint = Ship::move_waypoint(&waypoint, North, 3).unwrap();
+    assert_eq!(waypoint.east, 10);
+    assert_eq!(waypoint.north, 4);
 }
 
 #[test]
 fn test_move_ship_towards_waypoint() {
     let mut ship = Ship::new();
-    ship.move_ship_towards_waypoint(10);
-    assert_eq!(ship.east, 100.0);
-    assert_eq!(ship.north, 10.0);
+    ship.move_ship_towards_waypoint(10).unwrap();
+    assert_eq!(ship.position.east, 100);
+    assert_eq!(ship.position.north, 10);
 }
 
 #[test]
@@ -131,14 +133,210 @@ fn test_follow_instruction() {
     let mut ship = Ship::new();
 
     for instruction in input.iter() {
-        ship.follow_instruction(&MovementInstruction::compile(*instruction));
+        ship.follow_instruction(&MovementInstruction::compile(instruction).unwrap()).unwrap();
+    }
+
+    assert_eq!(ship.position.east, 214);
+    assert_eq!(ship.position.north, -72);
+
+    assert_eq!(ship.waypoint.east, 4);
+    assert_eq!(ship.waypoint.north, -10);
+
+    assert_eq!(ship.get_manhattan_distance(), 286);
+}
+
+#[test]
+fn test_rotate_waypoint_exactly() {
+    let waypoint = Waypoint { east: 123456789, north: -987654321 };
+
+    // A full turn in quarter steps lands exactly where it started
+    let mut rotated = waypoint;
+    for _ in 0..4 {
+        rotated = Ship::rotate_waypoint(&rotated, Left, 90).unwrap();
     }
+    assert_eq!(rotated, waypoint);
+
+    assert_eq!(Ship::rotate_waypoint(&waypoint, Right, 180).unwrap(), Waypoint { east: -123456789, north: 987654321 });
+    assert_eq!(Ship::rotate_waypoint(&waypoint, Left, 270).unwrap(), Ship::rotate_waypoint(&waypoint, Right, 90).unwrap());
+    assert_eq!(Ship::rotate_waypoint(&waypoint, Left, 360).unwrap(), waypoint);
+    assert_eq!(Ship::rotate_waypoint(&waypoint, Right, 450).unwrap(), Waypoint { east: -987654321, north: -123456789 });
+}
+
+#[test]
+fn test_invalid_rotation() {
+    assert_eq!(Ship::rotate(&North, Left, 45), Err(InvalidRotation { degrees: 45 }));
+    assert_eq!(Ship::rotate_waypoint(&Waypoint { east: 1, north: 0 }, Right, 100), Err(InvalidRotation { degrees: 100 }));
 
-    assert_eq!(ship.east, 214.0);
-    assert_eq!(ship.north, -72.0);
+    let mut ship = Ship::new();
+    assert_eq!(
+        ship.follow_instruction(&MovementInstruction::compile("R30").unwrap()),
+        Err(MovementError::InvalidRotation(InvalidRotation { degrees: 30 }))
+    );
+    assert_eq!(ship.waypoint, Waypoint { east: 10, north: 1 });
+    assert_eq!(
+        ship.move_ship(&MovementInstruction::compile("L181").unwrap()),
+        Err(MovementError::InvalidRotation(InvalidRotation { degrees: 181 }))
+    );
+    assert_eq!(ship.direction, East);
+}
 
-    assert_eq!(ship.waypoint.east, 4.0);
-    assert_eq!(ship.waypoint.north, -10.0);
+#[test]
+fn test_movement_out_of_range() {
+    let far = i64::MAX as usize;
+    assert_eq!(Ship::move_waypoint(&Waypoint { east: 0, north: 1 }, North, far), Err(MovementError::OutOfRange));
+    assert_eq!(Ship::move_waypoint(&Waypoint { east: 0, north: 0 }, South, far + 1), Err(MovementError::OutOfRange));
+    assert_eq!(Ship::move_waypoint(&Waypoint { east: 0, north: -1 }, South, far), Ok(Waypoint { east: 0, north: i64::MIN }));
+
+    // The waypoint starts 10 east, so a fifth of the way to the edge takes the ship twice past it
+    let mut ship = Ship::new();
+    assert_eq!(ship.move_ship_towards_waypoint(far / 5), Err(MovementError::OutOfRange));
+    assert_eq!(ship.position, Vector::default());
+
+    ship.move_ship(&MovementInstruction::East(far)).unwrap();
+    assert_eq!(ship.move_ship(&MovementInstruction::Forward(1)), Err(MovementError::OutOfRange));
+    assert_eq!(ship.follow_instruction(&MovementInstruction::Forward(far)), Err(MovementError::OutOfRange));
+}
+
+fn sample_instructions() -> Vec<MovementInstruction> {
+    ["F10", "N3", "F7", "R90", "F11"].iter()
+        .map(|instruction| MovementInstruction::compile(instruction).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_navigate_by_heading() {
+    let route = Route::navigate(&HeadingNavigation, &sample_instructions()).unwrap();
+
+    assert_eq!(route.ship.get_manhattan_distance(), 25);
+    assert_eq!(route.positions, vec![
+        Vector { east: 0, north: 0 },
+        Vector { east: 10, north: 0 },
+        Vector { east: 10, north: 3 },
+        Vector { east: 17, north: 3 },
+        Vector { east: 17, north: 3 },
+        Vector { east: 17, north: -8 },
+    ]);
+    assert_eq!(route.steering_vectors[4], Vector { east: 0, north: -1 });
+    assert_eq!(route.total_distance(), 31);
+    assert_eq!(route.farthest_distance(), 25);
+    assert_eq!(route.extent(), Extent { min: Vector { east: 0, north: -8 }, max: Vector { east: 17, north: 3 } });
+}
+
+#[test]
+fn test_navigate_by_waypoint() {
+    let route = Route::navigate(&WaypointNavigation, &sample_instructions()).unwrap();
+
+    assert_eq!(route.ship.get_manhattan_distance(), 286);
+    assert_eq!(route.positions.last(), Some(&Vector { east: 214, north: -72 }));
+    assert_eq!(route.steering_vectors.last(), Some(&Waypoint { east: 4, north: -10 }));
+    // 100 + 10, then 70 + 28, then 44 + 110
+    assert_eq!(route.total_distance(), 362);
+    assert_eq!(route.farthest_distance(), 286);
+}
+
+#[test]
+fn test_navigate_reports_bad_rotation() {
+    let mut instructions = sample_instructions();
+    instructions.insert(2, MovementInstruction::compile("L45").unwrap());
 
-    assert_eq!(ship.get_manhattan_distance(), 286.0);
-}
\ No newline at end of file
+    let error = Route::navigate(&WaypointNavigation, &instructions).unwrap_err();
+    assert_eq!(error, RouteError { line: 3, cause: MovementError::InvalidRotation(InvalidRotation { degrees: 45 }) });
+}
+
+#[test]
+fn test_route_to_svg() {
+    let route = Route::navigate(&HeadingNavigation, &sample_instructions()).unwrap();
+    let svg = route_to_svg(&route, 200);
+
+    assert!(svg.starts_with("<svg"));
+    assert!(svg.trim_end().ends_with("</svg>"));
+    assert_eq!(svg.matches("<polyline").count(), 1);
+    assert_eq!(svg.matches("<line").count(), route.positions.len());
+    assert_eq!(svg.matches("<circle").count(), 2);
+}
+
+#[test]
+fn test_render_png() {
+    let route = Route::navigate(&HeadingNavigation, &sample_instructions()).unwrap();
+    let image = render_png(&route, 200);
+
+    // The route spans 17 east and 11 north, so the image is wider than it is tall
+    assert_eq!(image.width(), 201);
+    assert!(image.height() < image.width());
+    assert_ne!(image.pixels().filter(|pixel| pixel[2] == 192).count(), 0);
+}
+
+#[test]
+fn test_compile_errors() {
+    assert_eq!(MovementInstruction::compile(""), Err(InstructionError::Empty));
+    assert_eq!(MovementInstruction::compile("X10"), Err(InstructionError::UnknownAction('X')));
+    assert_eq!(MovementInstruction::compile("F"), Err(InstructionError::InvalidOperand(String::from(""))));
+    assert_eq!(MovementInstruction::compile("F-3"), Err(InstructionError::InvalidOperand(String::from("-3"))));
+    assert_eq!(
+        MovementInstruction::compile("N9223372036854775808"),
+        Err(InstructionError::InvalidOperand(String::from("9223372036854775808")))
+    );
+    assert_eq!(MovementInstruction::compile("N9223372036854775807"), Ok(MovementInstruction::North(i64::MAX as usize)));
+    assert_eq!(MovementInstruction::compile("Ñ5"), Err(InstructionError::UnknownAction('Ñ')));
+}
+
+#[test]
+fn test_parse_instructions() {
+    let lines: Vec<String> = ["F10", "N3", "F7"].iter().map(|line| line.to_string()).collect();
+    let instructions = parse_instructions(&lines).unwrap();
+    assert_eq!(instructions, vec![
+        MovementInstruction::Forward(10),
+        MovementInstruction::North(3),
+        MovementInstruction::Forward(7),
+    ]);
+
+    let lines: Vec<String> = ["F10", "N3", "Q7", "Fx"].iter().map(|line| line.to_string()).collect();
+    assert_eq!(
+        parse_instructions(&lines),
+        Err(ParseError { line: 3, error: InstructionError::UnknownAction('Q') })
+    );
+}
+
+#[test]
+fn test_instruction_round_trip() {
+    for instruction in ["N1", "S22", "E333", "W0", "L90", "R270", "F7"].iter() {
+        assert_eq!(MovementInstruction::compile(instruction).unwrap().to_string(), *instruction);
+    }
+}
+
+fn sail_there_and_back(mode: &dyn NavigationMode, instructions: Vec<MovementInstruction>) -> Route {
+    let route = Route::navigate(mode, &instructions).unwrap();
+    let mut instructions = instructions;
+    instructions.extend(mode.plan_return_home(&route.ship));
+
+    Route::navigate(mode, &instructions).unwrap()
+}
+
+#[test]
+fn test_plan_return_home_by_heading() {
+    let route = Route::navigate(&HeadingNavigation, &sample_instructions()).unwrap();
+    let return_home = HeadingNavigation.plan_return_home(&route.ship);
+    assert_eq!(return_home, vec![MovementInstruction::North(8), MovementInstruction::West(17)]);
+
+    let route = sail_there_and_back(&HeadingNavigation, sample_instructions());
+    assert_eq!(route.ship.position, Vector::default());
+}
+
+#[test]
+fn test_plan_return_home_by_waypoint() {
+    // The ship ends at (214, -72) with the waypoint at (4, -10), so the waypoint has to move to
+    // (-214, 72)
+    let route = Route::navigate(&WaypointNavigation, &sample_instructions()).unwrap();
+    let return_home = WaypointNavigation.plan_return_home(&route.ship);
+    assert_eq!(return_home, vec![
+        MovementInstruction::North(82),
+        MovementInstruction::West(218),
+        MovementInstruction::Forward(1),
+    ]);
+
+    let route = sail_there_and_back(&WaypointNavigation, sample_instructions());
+    assert_eq!(route.ship.position, Vector::default());
+
+    // Already home, nothing to do
+    assert_eq!(WaypointNavigation.plan_return_home(&Ship::new()), vec![]);
+}
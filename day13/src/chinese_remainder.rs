@@ -0,0 +1,78 @@
+use std::fmt::{Display, Formatter};
+use core::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SolveError {
+    /// A congruence modulo zero (or less) doesn't describe any timestamps
+    InvalidModulus(i128),
+    /// The congruences contradict each other
+    NoSolution,
+    /// The combined modulus, or a step on the way to it, doesn't fit in an i128
+    Overflow,
+}
+
+impl Display for SolveError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SolveError::InvalidModulus(modulus) => write!(f, "{} isn't a valid modulus", modulus),
+            SolveError::NoSolution => write!(f, "The congruences contradict each other"),
+            SolveError::Overflow => write!(f, "The solution is too large to compute"),
+        }
+    }
+}
+
+/// The congruence `x ≡ remainder (mod modulus)`, with `0 <= remainder < modulus`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Congruence {
+    pub remainder: i128,
+    pub modulus: i128,
+}
+
+impl Congruence {
+    pub fn new(remainder: i128, modulus: i128) -> Result<Congruence, SolveError> {
+        if modulus <= 0 {
+            return Err(SolveError::InvalidModulus(modulus));
+        }
+
+        Ok(Congruence { remainder: remainder.rem_euclid(modulus), modulus })
+    }
+}
+
+/// Returns `(g, x, y)` such that `a * x + b * y = g = gcd(a, b)`.
+fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        (a, 1, 0)
+    } else {
+        let (g, x, y) = extended_gcd(b, a % b);
+        (g, y, x - (a / b) * y)
+    }
+}
+
+/// Merges two congruences into the one that holds exactly when both of them do. The moduli don't
+/// need to be coprime, but if they share a factor the remainders have to agree on it, otherwise
+/// there's no solution.
+pub fn combine(first: Congruence, second: Congruence) -> Result<Congruence, SolveError> {
+    let (g, inverse, _) = extended_gcd(first.modulus, second.modulus);
+    let difference = second.remainder - first.remainder;
+    if difference % g != 0 {
+        return Err(SolveError::NoSolution);
+    }
+
+    // first.remainder + first.modulus * k ≡ second.remainder (mod second.modulus)
+    let reduced_modulus = second.modulus / g;
+    let k = ((difference / g) % reduced_modulus).checked_mul(inverse)
+        .ok_or(SolveError::Overflow)?
+        .rem_euclid(reduced_modulus);
+    let modulus = first.modulus.checked_mul(reduced_modulus).ok_or(SolveError::Overflow)?;
+
+    // k < reduced_modulus, so this is less than the combined modulus and can't overflow
+    Congruence::new(first.remainder + first.modulus * k, modulus)
+}
+
+/// Solves a system of congruences with the generalized Chinese Remainder Theorem. The result
+/// describes every solution; its remainder is the smallest non-negative one.
+pub fn solve(congruences: &[Congruence]) -> Result<Congruence, SolveError> {
+    congruences.iter().try_fold(Congruence { remainder: 0, modulus: 1 }, |solution, congruence| {
+        combine(solution, *congruence)
+    })
+}
@@ -1,43 +1,44 @@
+#[cfg(test)]
 mod test;
+mod chinese_remainder;
+mod timetable;
 
 use std::path::Path;
 use std::fs::File;
 use std::io::{BufReader, BufRead};
+use crate::timetable::{Timetable, AlignmentError};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Bus {
-    id: u32,
-    offset: u32
+    id: u64,
+    offset: u64
 }
 
 impl Bus {
-    pub fn time_since_last_departure(&self, from_timestamp: u32) -> u32 {
+    pub fn time_since_last_departure(&self, from_timestamp: u64) -> u64 {
         from_timestamp % self.id
     }
 
-    pub fn time_till_next_departure(&self, starting_from_timestamp: u32) -> u32 {
+    pub fn time_till_next_departure(&self, starting_from_timestamp: u64) -> u64 {
         self.id - self.time_since_last_departure(starting_from_timestamp)
     }
 }
 
 struct Input {
-    earliest_arrival_timestamp: u32,
+    earliest_arrival_timestamp: u64,
     busses: Vec<Option<Bus>>,
 }
 
 impl Input {
     pub fn get_valid_busses(&self) -> Vec<&Bus> {
-        let mut valid_busses = vec![];
-        for bus in self.busses.iter() {
-            if let Some(bus) = bus {
-                valid_busses.push(bus);
-            }
-        }
+        self.busses.iter().flatten().collect()
+    }
 
-        valid_busses
+    pub fn timetable(&self) -> Result<Timetable, AlignmentError> {
+        Timetable::new(self.get_valid_busses().into_iter().cloned().collect())
     }
 
-    pub fn get_earliest_available_bus_and_wait_time(&self) -> (&Bus, u32) {
+    pub fn get_earliest_available_bus_and_wait_time(&self) -> (&Bus, u64) {
         let busses = self.get_valid_busses();
 
         let mut wait_time = busses[0].time_till_next_departure(self.earliest_arrival_timestamp);
@@ -54,14 +55,13 @@ impl Input {
         (earliest_available_bus, wait_time)
     }
 
-    pub fn solve2(&self) {
-        println!("Solve using these equations at: https://www.dcode.fr/chinese-remainder");
+    /// Finds the earliest timestamp at which every bus departs `offset` minutes after it.
+    pub fn solve2(&self) -> Result<u64, AlignmentError> {
+        let offsets: Vec<(u64, u64)> = self.get_valid_busses().iter()
+            .map(|bus| (bus.id, bus.offset))
+            .collect();
 
-        let busses = self.get_valid_busses();
-        for bus in busses {
-            let remainder = bus.id as i32 - (bus.offset as i32 % bus.id as i32);
-            println!("x = {} mod {}", remainder.abs(), bus.id);
-        }
+        self.timetable()?.next_alignment(0, &offsets)
     }
 }
 
@@ -73,8 +73,8 @@ fn notes_to_busses(note: &str) -> Vec<Option<Bus>> {
         if bus_id == "x" {
             busses.push(None);
         } else {
-            let bus_id = bus_id.parse::<u32>().unwrap();
-            busses.push(Some(Bus { id: bus_id, offset: offset as u32 }));
+            let bus_id = bus_id.parse::<u64>().unwrap();
+            busses.push(Some(Bus { id: bus_id, offset: offset as u64 }));
         }
     }
 
@@ -88,7 +88,7 @@ fn get_input(filename: &str) -> Input {
     let first_line = lines.next().unwrap().unwrap();
     let second_line = lines.next().unwrap().unwrap();
 
-    let earliest_arrival_timestamp = first_line.parse::<u32>().unwrap();
+    let earliest_arrival_timestamp = first_line.parse::<u64>().unwrap();
     let busses = notes_to_busses(&second_line[..]);
 
     Input { earliest_arrival_timestamp, busses }
@@ -99,5 +99,24 @@ fn main() {
     let (bus, wait_time) = input.get_earliest_available_bus_and_wait_time();
     println!("Bus id: {}; Wait time: {}", bus.id, wait_time);
     println!("Result part 1: {}", bus.id * wait_time);
-    input.solve2();
+
+    match input.timetable() {
+        Ok(timetable) => {
+            let arrival = input.earliest_arrival_timestamp;
+            println!("Departures in the 10 minutes after arriving at {}:", arrival);
+            for departure in timetable.departures_between(arrival, arrival.saturating_add(10)) {
+                println!("  {} bus {}", departure.timestamp, departure.bus_id);
+            }
+            println!("Next departure of every bus:");
+            for departure in timetable.departure_sequence(arrival) {
+                println!("  {} bus {}", departure.timestamp, departure.bus_id);
+            }
+        }
+        Err(error) => println!("No timetable: {}", error),
+    }
+
+    match input.solve2() {
+        Ok(timestamp) => println!("Result part 2: {}", timestamp),
+        Err(error) => println!("Result part 2: no solution. {}", error),
+    }
 }
@@ -1,4 +1,6 @@
-use crate::{notes_to_busses, Bus, Input};
+use crate::{notes_to_busses, Bus, Input, chinese_remainder};
+use crate::chinese_remainder::{Congruence, SolveError};
+use crate::timetable::{Timetable, AlignmentError, Departure};
 
 #[test]
 fn test_note_to_busses() {
@@ -55,4 +57,155 @@ fn test_get_earliest_available_bus_and_wait_time() {
     let (bus, wait_time) = input.get_earliest_available_bus_and_wait_time();
     assert_eq!(bus.id, 59);
     assert_eq!(wait_time, 5);
-}
\ No newline at end of file
+}
+
+fn input_with_busses(note: &str) -> Input {
+    Input { earliest_arrival_timestamp: 0, busses: notes_to_busses(note) }
+}
+
+#[test]
+fn test_solve2() {
+    assert_eq!(input_with_busses("7,13,x,x,59,x,31,19").solve2(), Ok(1068781));
+    assert_eq!(input_with_busses("17,x,13,19").solve2(), Ok(3417));
+    assert_eq!(input_with_busses("67,7,59,61").solve2(), Ok(754018));
+    assert_eq!(input_with_busses("67,x,7,59,61").solve2(), Ok(779210));
+    assert_eq!(input_with_busses("67,7,x,59,61").solve2(), Ok(1261476));
+    assert_eq!(input_with_busses("1789,37,47,1889").solve2(), Ok(1202161486));
+}
+
+#[test]
+fn test_solve2_with_shared_factors() {
+    // t ≡ 0 (mod 4) and t ≡ -2 (mod 6) agree modulo 2, so t ≡ 4 (mod 12)
+    assert_eq!(input_with_busses("4,x,6").solve2(), Ok(4));
+    // t ≡ 0 (mod 4) and t ≡ -1 (mod 6) disagree modulo 2
+    assert_eq!(input_with_busses("4,6").solve2(), Err(AlignmentError::NeverAligns));
+}
+
+#[test]
+fn test_chinese_remainder_solve() {
+    let congruence = |remainder, modulus| Congruence::new(remainder, modulus).unwrap();
+
+    let solution = chinese_remainder::solve(&[
+        congruence(2, 3),
+        congruence(3, 5),
+        congruence(2, 7),
+    ]);
+    assert_eq!(solution, Ok(Congruence { remainder: 23, modulus: 105 }));
+
+    assert_eq!(chinese_remainder::solve(&[]), Ok(Congruence { remainder: 0, modulus: 1 }));
+    assert_eq!(
+        chinese_remainder::solve(&[congruence(3, 10), congruence(8, 15)]),
+        Ok(Congruence { remainder: 23, modulus: 30 })
+    );
+    assert_eq!(chinese_remainder::solve(&[congruence(3, 10), congruence(7, 15)]), Err(SolveError::NoSolution));
+}
+
+#[test]
+fn test_chinese_remainder_zero_modulus() {
+    assert_eq!(Congruence::new(3, 0), Err(SolveError::InvalidModulus(0)));
+    assert_eq!(Congruence::new(3, -5), Err(SolveError::InvalidModulus(-5)));
+
+    assert_eq!(input_with_busses("7,0").solve2(), Err(AlignmentError::ZeroBusId));
+}
+
+#[test]
+fn test_chinese_remainder_overflow() {
+    // Pairwise coprime moduli close to 2^63, whose product is far beyond an i128
+    let moduli = [1 << 62, 3i128.pow(39), 5i128.pow(27)];
+    let congruences: Vec<Congruence> = moduli.iter()
+        .map(|modulus| Congruence::new(1, *modulus).unwrap())
+        .collect();
+    assert_eq!(chinese_remainder::solve(&congruences), Err(SolveError::Overflow));
+
+    let note = format!("{},{},{}", moduli[0], moduli[1], moduli[2]);
+    assert_eq!(input_with_busses(&note).solve2(), Err(AlignmentError::Overflow));
+}
+
+fn sample_timetable() -> Timetable {
+    input_with_busses("7,13,x,x,59,x,31,19").timetable().unwrap()
+}
+
+#[test]
+fn test_departures_between() {
+    let departures = sample_timetable().departures_between(936, 950);
+    let departures: Vec<(u64, u64)> = departures.iter()
+        .map(|departure| (departure.timestamp, departure.bus_id))
+        .collect();
+    assert_eq!(departures, vec![(936, 13), (938, 7), (944, 59), (945, 7), (949, 13)]);
+
+    assert_eq!(sample_timetable().departures_between(0, 1).len(), 5);
+    assert!(sample_timetable().departures_between(939, 939).is_empty());
+}
+
+#[test]
+fn test_departures_near_the_end_of_time() {
+    // The next departure of each of these busses would be past u64::MAX
+    let departures = sample_timetable().departures_between(u64::MAX - 8, u64::MAX);
+    let departures: Vec<(u64, u64)> = departures.iter()
+        .map(|departure| (departure.timestamp, departure.bus_id))
+        .collect();
+    assert_eq!(departures, vec![(u64::MAX - 8, 7), (u64::MAX - 4, 59), (u64::MAX - 2, 13), (u64::MAX - 1, 7)]);
+
+    // Only the busses that still leave are in the sequence
+    let timetable = input_with_busses("7,13").timetable().unwrap();
+    let sequence = timetable.departure_sequence(u64::MAX - 1);
+    assert_eq!(sequence, vec![Departure { timestamp: u64::MAX - 1, bus_id: 7 }]);
+    assert!(timetable.departure_sequence(u64::MAX).is_empty());
+}
+
+#[test]
+fn test_timetable_with_zero_bus_id() {
+    assert_eq!(input_with_busses("7,x,0").timetable().err(), Some(AlignmentError::ZeroBusId));
+}
+
+#[test]
+fn test_departure_sequence() {
+    let sequence: Vec<u64> = sample_timetable().departure_sequence(939).iter()
+        .map(|departure| departure.bus_id)
+        .collect();
+    assert_eq!(sequence, vec![59, 7, 13, 19, 31]);
+
+    // A bus leaving right as we arrive doesn't make us wait
+    let sequence = sample_timetable().departure_sequence(938);
+    assert_eq!(sequence[0].timestamp, 938);
+    assert_eq!(sequence[0].bus_id, 7);
+}
+
+#[test]
+fn test_next_alignment() {
+    let timetable = sample_timetable();
+    assert_eq!(timetable.next_alignment(0, &[(7, 0), (13, 1)]), Ok(77));
+    assert_eq!(timetable.next_alignment(78, &[(7, 0), (13, 1)]), Ok(77 + 91));
+    assert_eq!(timetable.next_alignment(77, &[(7, 0), (13, 1)]), Ok(77));
+
+    // Well past what fits in 32 bits
+    let aligned = timetable.next_alignment(10_000_000_000, &[(59, 4), (31, 6), (19, 7)]).unwrap();
+    assert!(aligned >= 10_000_000_000);
+    assert_eq!((aligned + 4) % 59, 0);
+    assert_eq!((aligned + 6) % 31, 0);
+    assert_eq!((aligned + 7) % 19, 0);
+    assert!(aligned - 59 * 31 * 19 < 10_000_000_000);
+
+    assert_eq!(timetable.next_alignment(0, &[(8, 0)]), Err(AlignmentError::UnknownBus(8)));
+
+    let timetable = input_with_busses("4,6").timetable().unwrap();
+    assert_eq!(timetable.next_alignment(0, &[(4, 0), (6, 1)]), Err(AlignmentError::NeverAligns));
+}
+
+#[test]
+fn test_next_alignment_beyond_64_bits() {
+    // The combined period fits in an i128, but the first alignment is far past u64::MAX
+    let (first, second) = (3u64.pow(39), 5u64.pow(27));
+    let timetable = input_with_busses(&format!("{},{}", first, second)).timetable().unwrap();
+    assert_eq!(timetable.next_alignment(0, &[(first, 0), (second, 1)]), Err(AlignmentError::Overflow));
+
+    // An alignment that fits on its own can still be pushed past the end by `after`
+    let timetable = input_with_busses("7,13").timetable().unwrap();
+    assert_eq!(timetable.next_alignment(u64::MAX - 10, &[(7, 0), (13, 1)]), Err(AlignmentError::Overflow));
+}
+
+#[test]
+fn test_time_till_next_departure_with_large_timestamps() {
+    let bus = Bus{id: 59, offset: 0};
+    assert_eq!(bus.time_till_next_departure(5_000_000_000), 59 - 5_000_000_000 % 59);
+}
@@ -0,0 +1,120 @@
+use crate::Bus;
+use crate::chinese_remainder::{self, Congruence, SolveError};
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter};
+use core::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Departure {
+    pub timestamp: u64,
+    pub bus_id: u64,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum AlignmentError {
+    /// The bus isn't in the timetable
+    UnknownBus(u64),
+    /// The busses can never depart at the requested offsets from each other
+    NeverAligns,
+    /// A bus with id 0 never departs again
+    ZeroBusId,
+    /// The busses do line up, but not within 64-bit timestamps
+    Overflow,
+}
+
+impl Display for AlignmentError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            AlignmentError::UnknownBus(bus_id) => write!(f, "There's no bus {} in the timetable", bus_id),
+            AlignmentError::NeverAligns => write!(f, "The busses never line up like that"),
+            AlignmentError::ZeroBusId => write!(f, "Bus 0 doesn't have a schedule"),
+            AlignmentError::Overflow => write!(f, "The busses don't line up before timestamps run out"),
+        }
+    }
+}
+
+/// Every bus departs from the station at each multiple of its id, until timestamps run out.
+pub struct Timetable {
+    busses: Vec<Bus>,
+}
+
+impl Timetable {
+    /// Fails if there's a bus 0, since it would never leave.
+    pub fn new(busses: Vec<Bus>) -> Result<Timetable, AlignmentError> {
+        if busses.iter().any(|bus| bus.id == 0) {
+            return Err(AlignmentError::ZeroBusId);
+        }
+        Ok(Timetable { busses })
+    }
+
+    /// The earliest departure of a bus at or after `timestamp`, if there's one before the
+    /// timestamps run out.
+    fn next_departure(bus: &Bus, timestamp: u64) -> Option<Departure> {
+        let wait_time = (bus.id - bus.time_since_last_departure(timestamp)) % bus.id;
+        let timestamp = timestamp.checked_add(wait_time)?;
+        Some(Departure { timestamp, bus_id: bus.id })
+    }
+
+    /// All departures from `start` up to, but not including, `end`, in the order they happen.
+    /// Busses that leave together are ordered by id.
+    pub fn departures_between(&self, start: u64, end: u64) -> Vec<Departure> {
+        let mut departures = vec![];
+        for bus in self.busses.iter() {
+            let mut next_departure = Timetable::next_departure(bus, start);
+            while let Some(departure) = next_departure.filter(|departure| departure.timestamp < end) {
+                departures.push(departure);
+                next_departure = departure.timestamp.checked_add(bus.id)
+                    .map(|timestamp| Departure { timestamp, bus_id: bus.id });
+            }
+        }
+
+        departures.sort_by_key(|departure| (departure.timestamp, departure.bus_id));
+        departures
+    }
+
+    /// The next departure of every bus for someone arriving at the station at `arrival_timestamp`,
+    /// soonest first. Busses that don't leave again before the timestamps run out are left out.
+    pub fn departure_sequence(&self, arrival_timestamp: u64) -> Vec<Departure> {
+        let mut departures: Vec<Departure> = self.busses.iter()
+            .filter_map(|bus| Timetable::next_departure(bus, arrival_timestamp))
+            .collect();
+
+        departures.sort_by_key(|departure| (departure.timestamp, departure.bus_id));
+        departures
+    }
+
+    /// Finds the earliest timestamp `t`, at or after `after`, such that each of the `(bus id,
+    /// offset)` pairs has its bus departing at `t + offset`.
+    pub fn next_alignment(&self, after: u64, offsets: &[(u64, u64)]) -> Result<u64, AlignmentError> {
+        let mut congruences = vec![];
+        for (bus_id, offset) in offsets.iter() {
+            if !self.busses.iter().any(|bus| bus.id == *bus_id) {
+                return Err(AlignmentError::UnknownBus(*bus_id));
+            }
+
+            // Bus departs at t + offset  <=>  t ≡ -offset (mod id)
+            let congruence = Congruence::new(-(*offset as i128), *bus_id as i128)
+                .map_err(|_| AlignmentError::ZeroBusId)?;
+            congruences.push(congruence);
+        }
+
+        let solution = chinese_remainder::solve(&congruences).map_err(|error| match error {
+            SolveError::InvalidModulus(_) => AlignmentError::ZeroBusId,
+            SolveError::NoSolution => AlignmentError::NeverAligns,
+            SolveError::Overflow => AlignmentError::Overflow,
+        })?;
+
+        // Every solution is the remainder plus some multiple of the modulus; skip ahead to the
+        // first one that isn't before `after`
+        let after = after as i128;
+        let mut aligned = solution.remainder;
+        if aligned < after {
+            let periods_to_skip = (after - aligned - 1) / solution.modulus + 1;
+            aligned = periods_to_skip.checked_mul(solution.modulus)
+                .and_then(|skipped| skipped.checked_add(aligned))
+                .ok_or(AlignmentError::Overflow)?;
+        }
+
+        u64::try_from(aligned).map_err(|_| AlignmentError::Overflow)
+    }
+}
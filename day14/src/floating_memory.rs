@@ -0,0 +1,107 @@
+/*
+A set of addresses described by a ternary pattern: every address that matches `value` on all the
+bits that aren't in `floating`. Bits of `value` that are floating are always kept at 0, so two
+patterns describe the same addresses exactly when they're equal.
+ */
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct AddressPattern {
+    pub value: u64,
+    pub floating: u64,
+}
+
+impl AddressPattern {
+    pub fn new(value: u64, floating: u64) -> AddressPattern {
+        AddressPattern { value: value & !floating, floating }
+    }
+
+    pub fn number_of_addresses(&self) -> u64 {
+        1 << self.floating.count_ones()
+    }
+
+    pub fn intersects(&self, other: &AddressPattern) -> bool {
+        let fixed_in_both = !self.floating & !other.floating;
+        (self.value ^ other.value) & fixed_in_both == 0
+    }
+
+    /*
+    Splits the addresses in self that aren't in other into disjoint patterns.
+
+    Going through the bits that float in self but are fixed in other one at a time, we peel off
+    the half of the remaining addresses that disagree with other on that bit. The half that agrees
+    carries on to the next bit. Whatever is left at the end is inside other, and gets dropped.
+     */
+    pub fn subtract(&self, other: &AddressPattern) -> Vec<AddressPattern> {
+        if !self.intersects(other) {
+            return vec![*self];
+        }
+
+        let mut remaining = *self;
+        let mut pieces = vec![];
+        let mut bits_to_split = self.floating & !other.floating;
+
+        while bits_to_split != 0 {
+            let bit = bits_to_split & bits_to_split.wrapping_neg();
+            bits_to_split ^= bit;
+
+            let floating = remaining.floating & !bit;
+            pieces.push(AddressPattern::new(remaining.value | (!other.value & bit), floating));
+            remaining = AddressPattern::new(remaining.value | (other.value & bit), floating);
+        }
+
+        pieces
+    }
+
+    /// Lists every address in the pattern. There are 2^(floating bits) of them, so only use this
+    /// for small patterns.
+    pub fn addresses(&self) -> Vec<u64> {
+        let mut addresses = vec![self.value];
+        let mut floating = self.floating;
+
+        while floating != 0 {
+            let bit = floating & floating.wrapping_neg();
+            floating ^= bit;
+
+            let with_bit_set: Vec<u64> = addresses.iter().map(|address| address | bit).collect();
+            addresses.extend(with_bit_set);
+        }
+
+        addresses
+    }
+}
+
+/*
+Memory for the version 2 decoder, which writes to whole patterns of addresses at once. Rather
+than enumerating them, we store the patterns themselves, and keep them disjoint by carving each
+new write out of the ones before it. Patterns holding 0 don't add anything to the sum, so once
+they've done their carving they aren't stored.
+ */
+#[derive(Debug, Default)]
+pub struct FloatingMemory {
+    regions: Vec<(AddressPattern, u64)>,
+}
+
+impl FloatingMemory {
+    pub fn new() -> FloatingMemory {
+        FloatingMemory { regions: vec![] }
+    }
+
+    pub fn write(&mut self, pattern: AddressPattern, value: u64) {
+        let mut regions = Vec::with_capacity(self.regions.len() + 1);
+        for (region, region_value) in self.regions.iter() {
+            for piece in region.subtract(&pattern) {
+                regions.push((piece, *region_value));
+            }
+        }
+
+        if value != 0 {
+            regions.push((pattern, value));
+        }
+        self.regions = regions;
+    }
+
+    pub fn sum(&self) -> u128 {
+        self.regions.iter()
+            .map(|(region, value)| region.number_of_addresses() as u128 * *value as u128)
+            .sum()
+    }
+}
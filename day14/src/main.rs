@@ -1,18 +1,35 @@
+#[cfg(test)]
 mod tests;
+mod floating_memory;
 
 use std::collections::HashMap;
-use regex;
+use crate::floating_memory::{AddressPattern, FloatingMemory};
+use regex::Regex;
 use std::path::Path;
 use std::fs::File;
 use std::io::{BufReader, BufRead};
+use std::fmt::{Display, Formatter};
+use std::sync::OnceLock;
+use std::env;
+use std::process;
+use core::fmt;
+
+const WORD_SIZE: usize = 36;
+const WORD_MASK: u64 = (1 << WORD_SIZE) - 1;
+
+static MEMORY_INSTRUCTION: OnceLock<Regex> = OnceLock::new();
 
 /*
 We only keep the latest mask, and only store those memory locations which have been set to non-zero.
 This let's us not have to allocate ~68GB of memory for it. Seems like a good thing.
+
+Version 2 of the chip writes to a whole pattern of addresses at a time, so it keeps its memory as
+patterns in floating_memory instead.
  */
 struct DecoderChip {
     mask: CompiledMask,
     set_memory_locations: HashMap<u64, u64>,
+    floating_memory: FloatingMemory,
 }
 
 /*
@@ -26,7 +43,7 @@ For bits that need to be set to 0, we set those bits in the "and value" to 0.
 
 To apply, we use the & and | operators with the corresponding values on the incoming data.
  */
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone)]
 struct CompiledMask {
     or_value: u64,
     and_value: u64,
@@ -38,36 +55,97 @@ impl CompiledMask {
         (to | self.or_value) & self.and_value
     }
 
-    fn apply_floating_mask(indices: &[u8], to: u64) -> Vec<u64> {
-        if indices.is_empty() {
-            return vec![to];
-        }
-
-        let index_to_work_on = indices[0];
-        let zero_mask = 0xFFFFFFFFFFFFFFFFu64 ^ (1 << index_to_work_on);
-        let one_mask = 0u64 | (1 << index_to_work_on);
+    pub fn floating_bits(&self) -> u64 {
+        self.floating_indices.iter().fold(0, |bits, index| bits | (1 << index))
+    }
 
-        let zero_value = to & zero_mask;
-        let one_value = to | one_mask;
+    /// The addresses a version 2 write to `to` ends up at.
+    pub fn address_pattern(&self, to: u64) -> AddressPattern {
+        AddressPattern::new(to | self.or_value, self.floating_bits())
+    }
+}
 
-        let mut options_with_zero_value = CompiledMask::apply_floating_mask(&indices[1..], zero_value);
-        let mut options_with_one_value = CompiledMask::apply_floating_mask(&indices[1..], one_value);
+#[derive(PartialEq, Debug)]
+enum Instruction {
+    SetMask(CompiledMask),
+    SetMemory(u64, u64),
+}
 
-        let mut all_options = options_with_zero_value;
-        all_options.append(&mut options_with_one_value);
+#[derive(PartialEq, Debug)]
+enum InstructionError {
+    MissingAssignment,
+    UnknownInstruction(String),
+    InvalidMaskCharacter(char),
+    InvalidMaskWidth(usize),
+    InvalidNumber(String),
+    ValueTooWide(u64),
+}
 
-        all_options
+impl Display for InstructionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            InstructionError::MissingAssignment => write!(f, "Expected an instruction of the form `target = value`"),
+            InstructionError::UnknownInstruction(target) => write!(f, "Unknown instruction `{}`", target),
+            InstructionError::InvalidMaskCharacter(c) => write!(f, "Masks can only contain 0, 1 and X, not '{}'", c),
+            InstructionError::InvalidMaskWidth(width) => write!(f, "Masks are {} bits wide, not {}", WORD_SIZE, width),
+            InstructionError::InvalidNumber(number) => write!(f, "`{}` is not a number", number),
+            InstructionError::ValueTooWide(value) => write!(f, "{} doesn't fit in {} bits", value, WORD_SIZE),
+        }
     }
+}
 
-    pub fn apply_mask_v2(&self, to: u64) -> Vec<u64> {
-        CompiledMask::apply_floating_mask(&self.floating_indices, to | self.or_value)
+/// An instruction that doesn't compile, and the (1-based) line of the program it's on.
+#[derive(PartialEq, Debug)]
+struct ProgramError {
+    line: usize,
+    error: InstructionError,
+}
+
+impl Display for ProgramError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Line {}: {}", self.line, self.error)
     }
 }
 
+/// What a single memory write did, for tracing a program.
 #[derive(PartialEq, Debug)]
-enum Instruction {
-    SetMask(String),
-    SetMemory(u64, u64),
+enum TraceEvent {
+    MaskedWrite { line: usize, address: u64, value: u64, masked_value: u64 },
+    FloatingWrite { line: usize, addresses: AddressPattern, value: u64 },
+}
+
+/// Patterns with more addresses than this are only shown as a pattern in the trace.
+const MAX_TRACED_ADDRESSES: u64 = 16;
+
+impl Display for TraceEvent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceEvent::MaskedWrite { line, address, value, masked_value } => {
+                write!(f, "{}: mem[{}] = {} -> {}", line, address, value, masked_value)
+            }
+            TraceEvent::FloatingWrite { line, addresses, value } => {
+                let pattern: String = (0..WORD_SIZE).rev().map(|bit| {
+                    if addresses.floating & (1 << bit) != 0 {
+                        'X'
+                    } else if addresses.value & (1 << bit) != 0 {
+                        '1'
+                    } else {
+                        '0'
+                    }
+                }).collect();
+
+                write!(f, "{}: mem[{}] = {}", line, pattern, value)?;
+                if addresses.number_of_addresses() <= MAX_TRACED_ADDRESSES {
+                    let mut listed = addresses.addresses();
+                    listed.sort_unstable();
+                    let listed: Vec<String> = listed.iter().map(|address| address.to_string()).collect();
+                    write!(f, " -> {}", listed.join(", "))
+                } else {
+                    write!(f, " -> {} addresses", addresses.number_of_addresses())
+                }
+            }
+        }
+    }
 }
 
 impl DecoderChip {
@@ -75,29 +153,45 @@ impl DecoderChip {
         DecoderChip {
             mask: CompiledMask { or_value: 0, and_value: 0, floating_indices: vec![] },
             set_memory_locations: HashMap::new(),
+            floating_memory: FloatingMemory::new(),
         }
     }
-    pub fn compile_instruction(instruction: &str) -> Instruction {
-        let mut instruction_parts = instruction.split(" = ");
-        let operator = instruction_parts.next();
-        let operand = instruction_parts.next();
+    fn parse_word(number: &str) -> Result<u64, InstructionError> {
+        let value = number.parse::<u64>()
+            .map_err(|_| InstructionError::InvalidNumber(String::from(number)))?;
 
-        let operator = operator.unwrap();
-        let operand = operand.unwrap();
+        if value > WORD_MASK {
+            return Err(InstructionError::ValueTooWide(value));
+        }
+        Ok(value)
+    }
+
+    pub fn compile_instruction(instruction: &str) -> Result<Instruction, InstructionError> {
+        let mut instruction_parts = instruction.split(" = ");
+        let operator = instruction_parts.next().unwrap_or("");
+        let operand = instruction_parts.next().ok_or(InstructionError::MissingAssignment)?;
 
         if operator == "mask" {
-            return Instruction::SetMask(String::from(operand));
+            return Ok(Instruction::SetMask(DecoderChip::compile_mask(operand)?));
         }
 
-        let operand = operand.parse::<u64>().unwrap();
-        let re = regex::Regex::new(r"^mem\[(\d+)\]$").unwrap();
-        let memory_address = re.captures(operator).unwrap().get(1).unwrap().as_str();
-        let memory_address = memory_address.parse::<u64>().unwrap();
+        let re = MEMORY_INSTRUCTION.get_or_init(|| Regex::new(r"^mem\[(\d+)\]$").unwrap());
+        let memory_address = re.captures(operator)
+            .ok_or_else(|| InstructionError::UnknownInstruction(String::from(operator)))?
+            .get(1).unwrap().as_str();
 
-        return Instruction::SetMemory(memory_address, operand);
+        let memory_address = DecoderChip::parse_word(memory_address)?;
+        let operand = DecoderChip::parse_word(operand)?;
+
+        Ok(Instruction::SetMemory(memory_address, operand))
     }
 
-    pub fn compile_mask(mask: &str) -> CompiledMask {
+    pub fn compile_mask(mask: &str) -> Result<CompiledMask, InstructionError> {
+        let width = mask.chars().count();
+        if width != WORD_SIZE {
+            return Err(InstructionError::InvalidMaskWidth(width));
+        }
+
         let (mut or_value, mut and_value) = (0u64, 0xFFFFFFFFFFFFFFFFu64);
         let mut floating_indices = vec![];
 
@@ -112,66 +206,76 @@ impl DecoderChip {
                 'X' => {
                     floating_indices.push(i as u8);
                 }
-                _ => ()
+                c => return Err(InstructionError::InvalidMaskCharacter(c))
             }
         }
 
-        CompiledMask { or_value, and_value, floating_indices }
+        Ok(CompiledMask { or_value, and_value, floating_indices })
     }
 
-    fn apply_instruction(&mut self, instruction: Instruction) {
-        match instruction {
-            Instruction::SetMask(mask) => {
-                self.mask = DecoderChip::compile_mask(&mask[..]);
+    /// Compiles the whole program up front, so nothing runs unless all of it is valid.
+    pub fn compile_program(program: &[String]) -> Result<Vec<Instruction>, ProgramError> {
+        program.iter()
+            .enumerate()
+            .map(|(index, instruction)| {
+                DecoderChip::compile_instruction(instruction)
+                    .map_err(|error| ProgramError { line: index + 1, error })
+            })
+            .collect()
+    }
+
+    fn apply_instruction(&mut self, instruction: &Instruction, line: usize) -> Option<TraceEvent> {
+        match *instruction {
+            Instruction::SetMask(ref mask) => {
+                self.mask = mask.clone();
+                None
             }
             Instruction::SetMemory(at, value) => {
-                self.set_memory_locations.insert(at, self.mask.apply_mask(value));
+                let masked_value = self.mask.apply_mask(value);
+                self.set_memory_locations.insert(at, masked_value);
+                Some(TraceEvent::MaskedWrite { line, address: at, value, masked_value })
             }
         }
     }
 
-    fn apply_instruction_v2(&mut self, instruction: Instruction) {
-        match instruction {
-            Instruction::SetMask(mask) => {
-                self.mask = DecoderChip::compile_mask(&mask[..]);
+    fn apply_instruction_v2(&mut self, instruction: &Instruction, line: usize) -> Option<TraceEvent> {
+        match *instruction {
+            Instruction::SetMask(ref mask) => {
+                self.mask = mask.clone();
+                None
             }
             Instruction::SetMemory(at, value) => {
-                for memory_locations in self.mask.apply_mask_v2(at) {
-                    self.set_memory_locations.insert(memory_locations, value);
-                }
+                let addresses = self.mask.address_pattern(at);
+                self.floating_memory.write(addresses, value);
+                Some(TraceEvent::FloatingWrite { line, addresses, value })
             }
         }
     }
 
-    pub fn compile_and_apply_instruction(&mut self, instruction: &str) {
-        let instruction = DecoderChip::compile_instruction(instruction);
-        self.apply_instruction(instruction);
+    /// Runs a compiled program, returning a trace of every memory write it made.
+    pub fn run_program(&mut self, program: &[Instruction]) -> Vec<TraceEvent> {
+        program.iter()
+            .enumerate()
+            .filter_map(|(index, instruction)| self.apply_instruction(instruction, index + 1))
+            .collect()
     }
 
-    pub fn compile_and_apply_instruction_v2(&mut self, instruction: &str) {
-        let instruction = DecoderChip::compile_instruction(instruction);
-        self.apply_instruction_v2(instruction);
+    /// Runs a compiled program on version 2 of the chip, returning a trace of every memory write
+    /// it made.
+    pub fn run_program_v2(&mut self, program: &[Instruction]) -> Vec<TraceEvent> {
+        program.iter()
+            .enumerate()
+            .filter_map(|(index, instruction)| self.apply_instruction_v2(instruction, index + 1))
+            .collect()
     }
 
-    pub fn run_program(&mut self, program: &Vec<String>) {
-        for instruction in program.iter() {
-            self.compile_and_apply_instruction(&instruction[..]);
-        }
-    }
-
-    pub fn run_program_v2(&mut self, program: &Vec<String>) {
-        for instruction in program.iter() {
-            self.compile_and_apply_instruction_v2(&instruction[..]);
-        }
-    }
-
-    pub fn sum_all_memory_values(&self) -> u64 {
+    pub fn sum_all_memory_values(&self) -> u128 {
         let mut sum = 0;
         for (_, value) in self.set_memory_locations.iter() {
-            sum += value;
+            sum += *value as u128;
         }
 
-        sum
+        sum + self.floating_memory.sum()
     }
 }
 
@@ -189,13 +293,41 @@ fn get_input(filename: &str) -> Vec<String> {
 }
 
 fn main() {
-    let input = get_input("input.txt");
+    // Usage: day14 [--trace] [input file]
+    let mut filename = String::from("input.txt");
+    let mut trace = false;
+    for arg in env::args().skip(1) {
+        match &arg[..] {
+            "--trace" => trace = true,
+            _ => filename = arg,
+        }
+    }
+
+    let input = get_input(&filename);
+    let program = match DecoderChip::compile_program(&input) {
+        Ok(program) => program,
+        Err(error) => {
+            println!("{}", error);
+            process::exit(1);
+        }
+    };
 
     let mut chip = DecoderChip::new();
-    chip.run_program(&input);
+    let trace_v1 = chip.run_program(&program);
     println!("Result part 1: {}", chip.sum_all_memory_values());
 
     let mut chip_v2 = DecoderChip::new();
-    chip_v2.run_program_v2(&input);
+    let trace_v2 = chip_v2.run_program_v2(&program);
     println!("Result part 2: {}", chip_v2.sum_all_memory_values());
+
+    if trace {
+        println!("Trace part 1:");
+        for event in trace_v1.iter() {
+            println!("  {}", event);
+        }
+        println!("Trace part 2:");
+        for event in trace_v2.iter() {
+            println!("  {}", event);
+        }
+    }
 }
@@ -1,35 +1,36 @@
-use crate::{DecoderChip, Instruction};
+use crate::{DecoderChip, Instruction, InstructionError, ProgramError, TraceEvent};
+use crate::floating_memory::{AddressPattern, FloatingMemory};
 use std::collections::HashSet;
-use std::iter::FromIterator;
 
 #[test]
 fn test_compile_instruction() {
-    let set_mask = DecoderChip::compile_instruction("mask = 00110X11X0000110X0000001000111010X00");
-    assert_eq!(set_mask, Instruction::SetMask(String::from("00110X11X0000110X0000001000111010X00")));
+    let set_mask = DecoderChip::compile_instruction("mask = 00110X11X0000110X0000001000111010X00").unwrap();
+    let mask = DecoderChip::compile_mask("00110X11X0000110X0000001000111010X00").unwrap();
+    assert_eq!(set_mask, Instruction::SetMask(mask));
 
-    let set_memory = DecoderChip::compile_instruction("mem[61385] = 13441");
+    let set_memory = DecoderChip::compile_instruction("mem[61385] = 13441").unwrap();
     assert_eq!(set_memory, Instruction::SetMemory(61385, 13441));
 }
 
 #[test]
 fn test_compile_mask() {
     let mask = "XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX0X";
-    let compiled_mask = DecoderChip::compile_mask(mask);
+    let compiled_mask = DecoderChip::compile_mask(mask).unwrap();
 
     assert_eq!(compiled_mask.or_value, 0b1000000, "Or mask");
     assert_eq!(compiled_mask.and_value,
                0b1111111111111111111111111111111111111111111111111111111111111101,
                "And mask");
 
-    let compiled_mask = DecoderChip::compile_mask("000000000000000000000000000000X1001X");
+    let compiled_mask = DecoderChip::compile_mask("000000000000000000000000000000X1001X").unwrap();
     assert_eq!(compiled_mask.floating_indices, vec![0, 5]);
 }
 
 #[test]
 fn test_apply_mask_v2() {
-    let mask = DecoderChip::compile_mask("000000000000000000000000000000X1001X");
-    let possible_values = mask.apply_mask_v2(42);
-    let expected_values = vec![26, 27, 58, 59];
+    let mask = DecoderChip::compile_mask("000000000000000000000000000000X1001X").unwrap();
+    let possible_values = mask.address_pattern(42).addresses();
+    let expected_values = [26, 27, 58, 59];
 
     let mut values_set = HashSet::new();
     for v in possible_values {
@@ -40,9 +41,9 @@ fn test_apply_mask_v2() {
         assert!(values_set.contains(v));
     }
 
-    let mask = DecoderChip::compile_mask("00000000000000000000000000000000X0XX");
-    let possible_values = mask.apply_mask_v2(26);
-    let expected_values = vec![16, 17, 18, 19, 24, 25, 26, 27];
+    let mask = DecoderChip::compile_mask("00000000000000000000000000000000X0XX").unwrap();
+    let possible_values = mask.address_pattern(26).addresses();
+    let expected_values = [16, 17, 18, 19, 24, 25, 26, 27];
 
     let mut values_set = HashSet::new();
     for v in possible_values {
@@ -57,29 +58,116 @@ fn test_apply_mask_v2() {
 #[test]
 fn test_apply_mask() {
     let mask = "XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX0X";
-    let mask = DecoderChip::compile_mask(mask);
+    let mask = DecoderChip::compile_mask(mask).unwrap();
 
     assert_eq!(mask.apply_mask(11), 73);
     assert_eq!(mask.apply_mask(101), 101);
     assert_eq!(mask.apply_mask(0), 64);
 }
 
+fn instruction(line: &str) -> Instruction {
+    DecoderChip::compile_instruction(line).unwrap()
+}
+
 #[test]
-fn test_compile_and_apply_instruction() {
+fn test_apply_instruction() {
     let mut chip = DecoderChip::new();
 
-    chip.compile_and_apply_instruction("mask = XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX0X");
+    chip.apply_instruction(&instruction("mask = XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX0X"), 1);
 
-    chip.compile_and_apply_instruction("mem[8] = 11");
+    chip.apply_instruction(&instruction("mem[8] = 11"), 2);
     assert_eq!(*chip.set_memory_locations.get(&8).unwrap(), 73);
 
-    chip.compile_and_apply_instruction("mem[7] = 101");
+    chip.apply_instruction(&instruction("mem[7] = 101"), 3);
     assert_eq!(*chip.set_memory_locations.get(&7).unwrap(), 101);
 
-    chip.compile_and_apply_instruction("mem[8] = 0");
+    chip.apply_instruction(&instruction("mem[8] = 0"), 4);
     assert_eq!(*chip.set_memory_locations.get(&8).unwrap(), 64);
 }
 
+#[test]
+fn test_apply_instruction_v2() {
+    let mut chip = DecoderChip::new();
+
+    chip.apply_instruction_v2(&instruction("mask = 000000000000000000000000000000X1001X"), 1);
+    chip.apply_instruction_v2(&instruction("mem[42] = 100"), 2);
+    assert_eq!(chip.sum_all_memory_values(), 400);
+}
+
+#[test]
+fn test_compile_instruction_errors() {
+    assert_eq!(DecoderChip::compile_instruction("mem[8] 11"), Err(InstructionError::MissingAssignment));
+    assert_eq!(DecoderChip::compile_instruction("mem[x] = 11"),
+               Err(InstructionError::UnknownInstruction(String::from("mem[x]"))));
+    assert_eq!(DecoderChip::compile_instruction("mem[8] = eleven"),
+               Err(InstructionError::InvalidNumber(String::from("eleven"))));
+    assert_eq!(DecoderChip::compile_instruction("mem[8] = 68719476736"),
+               Err(InstructionError::ValueTooWide(68719476736)));
+    assert_eq!(DecoderChip::compile_instruction("mem[68719476736] = 1"),
+               Err(InstructionError::ValueTooWide(68719476736)));
+    assert_eq!(DecoderChip::compile_instruction("mem[68719476735] = 68719476735"),
+               Ok(Instruction::SetMemory(68719476735, 68719476735)));
+}
+
+#[test]
+fn test_compile_mask_errors() {
+    assert_eq!(DecoderChip::compile_mask("XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX0").err(),
+               Some(InstructionError::InvalidMaskWidth(35)));
+    assert_eq!(DecoderChip::compile_mask("XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX0X1").err(),
+               Some(InstructionError::InvalidMaskWidth(37)));
+    assert_eq!(DecoderChip::compile_mask("XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX0x").err(),
+               Some(InstructionError::InvalidMaskCharacter('x')));
+    assert_eq!(DecoderChip::compile_instruction("mask = 0").err(),
+               Some(InstructionError::InvalidMaskWidth(1)));
+}
+
+#[test]
+fn test_program_is_validated_before_running() {
+    let program = vec![
+        String::from("mask = XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX0X"),
+        String::from("mem[8] = 11"),
+        String::from("mem[7] = -101"),
+    ];
+
+    let error = ProgramError { line: 3, error: InstructionError::InvalidNumber(String::from("-101")) };
+    assert_eq!(error.to_string(), "Line 3: `-101` is not a number");
+
+    assert_eq!(DecoderChip::compile_program(&program), Err(error));
+}
+
+fn compile(program: &[String]) -> Vec<Instruction> {
+    DecoderChip::compile_program(program).unwrap()
+}
+
+#[test]
+fn test_trace() {
+    let program = vec![
+        String::from("mask = 000000000000000000000000000000X1001X"),
+        String::from("mem[42] = 100"),
+        String::from("mask = 00000000000000000000000000000000X0XX"),
+        String::from("mem[26] = 1"),
+    ];
+
+    let trace = DecoderChip::new().run_program(&compile(&program));
+    assert_eq!(trace, vec![
+        TraceEvent::MaskedWrite { line: 2, address: 42, value: 100, masked_value: 50 },
+        TraceEvent::MaskedWrite { line: 4, address: 26, value: 1, masked_value: 1 },
+    ]);
+    assert_eq!(trace[0].to_string(), "2: mem[42] = 100 -> 50");
+
+    let trace = DecoderChip::new().run_program_v2(&compile(&program));
+    assert_eq!(trace.len(), 2);
+    assert_eq!(trace[0].to_string(), "2: mem[000000000000000000000000000000X1101X] = 100 -> 26, 27, 58, 59");
+    assert_eq!(trace[1].to_string(),
+               "4: mem[00000000000000000000000000000001X0XX] = 1 -> 16, 17, 18, 19, 24, 25, 26, 27");
+
+    let trace = DecoderChip::new().run_program_v2(&compile(&[
+        String::from("mask = 0000000000000000000000000000XXXXXXXX"),
+        String::from("mem[0] = 1"),
+    ]));
+    assert_eq!(trace[0].to_string(), "2: mem[0000000000000000000000000000XXXXXXXX] = 1 -> 256 addresses");
+}
+
 #[test]
 fn test_decoder_chip() {
     let program = vec![
@@ -89,6 +177,70 @@ fn test_decoder_chip() {
         String::from("mem[8] = 0"),
     ];
     let mut chip = DecoderChip::new();
-    chip.run_program(&program);
+    chip.run_program(&compile(&program));
     assert_eq!(chip.sum_all_memory_values(), 165);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_decoder_chip_v2() {
+    let program = vec![
+        String::from("mask = 000000000000000000000000000000X1001X"),
+        String::from("mem[42] = 100"),
+        String::from("mask = 00000000000000000000000000000000X0XX"),
+        String::from("mem[26] = 1"),
+    ];
+    let mut chip = DecoderChip::new();
+    chip.run_program_v2(&compile(&program));
+    assert_eq!(chip.sum_all_memory_values(), 208);
+}
+
+#[test]
+fn test_decoder_chip_v2_with_many_floating_bits() {
+    // Every address gets written, so only the last write to each one counts
+    let program = vec![
+        String::from("mask = XXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXX"),
+        String::from("mem[0] = 3"),
+        String::from("mask = 0XXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXX"),
+        String::from("mem[0] = 1"),
+        String::from("mask = 00000000000000000000000000000000000X"),
+        String::from("mem[6] = 0"),
+    ];
+    let mut chip = DecoderChip::new();
+    chip.run_program_v2(&compile(&program));
+
+    let addresses = 1u128 << 36;
+    assert_eq!(chip.sum_all_memory_values(), 3 * addresses / 2 + addresses / 2 - 2);
+}
+
+#[test]
+fn test_address_pattern_subtract() {
+    let pattern = AddressPattern::new(0b0000, 0b1111);
+    let hole = AddressPattern::new(0b0101, 0b0010);
+
+    let pieces = pattern.subtract(&hole);
+    let mut addresses: Vec<u64> = pieces.iter().flat_map(|piece| piece.addresses()).collect();
+    addresses.sort_unstable();
+    assert_eq!(addresses, vec![0, 1, 2, 3, 4, 6, 8, 9, 10, 11, 12, 13, 14, 15]);
+    assert_eq!(pieces.iter().map(AddressPattern::number_of_addresses).sum::<u64>(), 14);
+
+    // Disjoint patterns are left alone, and covered ones disappear
+    let other = AddressPattern::new(0b1000, 0b0001);
+    assert_eq!(other.subtract(&hole), vec![other]);
+    assert_eq!(hole.subtract(&pattern), vec![]);
+}
+
+#[test]
+fn test_floating_memory() {
+    let mut memory = FloatingMemory::new();
+    memory.write(AddressPattern::new(0, 0b111), 5);
+    assert_eq!(memory.sum(), 40);
+
+    memory.write(AddressPattern::new(0b100, 0b001), 1);
+    assert_eq!(memory.sum(), 6 * 5 + 2);
+
+    memory.write(AddressPattern::new(0, 0b010), 0);
+    assert_eq!(memory.sum(), 4 * 5 + 2);
+
+    memory.write(AddressPattern::new(0, 0b111), 0);
+    assert_eq!(memory.sum(), 0);
+}
@@ -0,0 +1,120 @@
+use crate::{LastSeenTable, MemoryGame};
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Statistics about the numbers spoken over the first `turns` turns of a memory game.
+pub struct Analysis {
+    pub turns: u32,
+    pub largest_number: u32,
+    pub zeros: u32,
+    // How many times each gap between a number being spoken again and the last time it was
+    // spoken came up, indexed by the gap
+    gap_counts: Vec<u32>,
+    /// Every distinct number spoken, as `(number, first turn)`, in the order they first came up.
+    pub first_appearances: Vec<(u32, u32)>,
+}
+
+impl Analysis {
+    pub fn new(starting_numbers: &[u32], turns: u32) -> Analysis {
+        let mut game = MemoryGame::new(starting_numbers);
+        // The game keeps its own table, but it's always a turn behind
+        let mut last_seen = LastSeenTable::new(starting_numbers.iter().copied().max().unwrap_or(0));
+        last_seen.reserve(turns as usize);
+
+        let mut analysis = Analysis {
+            turns,
+            largest_number: 0,
+            zeros: 0,
+            gap_counts: vec![],
+            first_appearances: vec![],
+        };
+
+        for turn in 1..=turns {
+            let number = game.next_number();
+
+            analysis.largest_number = analysis.largest_number.max(number);
+            if number == 0 {
+                analysis.zeros += 1;
+            }
+
+            match last_seen.record(number, turn) {
+                0 => analysis.first_appearances.push((number, turn)),
+                spoken_on => {
+                    let gap = (turn - spoken_on) as usize;
+                    if gap >= analysis.gap_counts.len() {
+                        analysis.gap_counts.resize(gap + 1, 0);
+                    }
+                    analysis.gap_counts[gap] += 1;
+                }
+            }
+        }
+
+        analysis
+    }
+
+    pub fn distinct_numbers(&self) -> usize {
+        self.first_appearances.len()
+    }
+
+    /// The gap distribution, as `(gap, count)` in order of the gap. Gaps that never came up are
+    /// left out.
+    pub fn gaps(&self) -> impl Iterator<Item=(u32, u32)> + '_ {
+        self.gap_counts.iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(gap, count)| (gap as u32, *count))
+    }
+
+    pub fn zero_frequency(&self) -> f64 {
+        if self.turns == 0 {
+            0.0
+        } else {
+            self.zeros as f64 / self.turns as f64
+        }
+    }
+
+    pub fn write_summary_csv<W: Write>(&self, mut out: W) -> io::Result<()> {
+        writeln!(out, "statistic,value")?;
+        writeln!(out, "turns,{}", self.turns)?;
+        writeln!(out, "distinct_numbers,{}", self.distinct_numbers())?;
+        writeln!(out, "largest_number,{}", self.largest_number)?;
+        writeln!(out, "zeros,{}", self.zeros)?;
+        writeln!(out, "zero_frequency,{}", self.zero_frequency())
+    }
+
+    pub fn write_gaps_csv<W: Write>(&self, mut out: W) -> io::Result<()> {
+        writeln!(out, "gap,count")?;
+        for (gap, count) in self.gaps() {
+            writeln!(out, "{},{}", gap, count)?;
+        }
+        Ok(())
+    }
+
+    pub fn write_first_appearances_csv<W: Write>(&self, mut out: W) -> io::Result<()> {
+        writeln!(out, "number,first_turn")?;
+        for (number, turn) in self.first_appearances.iter() {
+            writeln!(out, "{},{}", number, turn)?;
+        }
+        Ok(())
+    }
+
+    /// Writes `summary.csv`, `gaps.csv` and `first_turns.csv` into `directory`, creating it if
+    /// it doesn't exist yet.
+    pub fn write_csv(&self, directory: &Path) -> io::Result<()> {
+        std::fs::create_dir_all(directory)?;
+
+        let mut summary = BufWriter::new(File::create(directory.join("summary.csv"))?);
+        self.write_summary_csv(&mut summary)?;
+        summary.flush()?;
+
+        let mut gaps = BufWriter::new(File::create(directory.join("gaps.csv"))?);
+        self.write_gaps_csv(&mut gaps)?;
+        gaps.flush()?;
+
+        let mut first_appearances = BufWriter::new(File::create(directory.join("first_turns.csv"))?);
+        self.write_first_appearances_csv(&mut first_appearances)?;
+        first_appearances.flush()
+    }
+}
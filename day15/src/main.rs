@@ -1,85 +1,218 @@
 use std::collections::HashMap;
+use std::env;
+use std::mem;
+use std::path::Path;
+use std::process;
+use crate::analytics::Analysis;
 
+mod analytics;
+#[cfg(test)]
 mod tests;
 
+const USAGE: &str = "Usage: day15 <starting numbers, comma separated> [turn ...] [--analyse] [--csv DIRECTORY]";
+
+/// Numbers below this are tracked in a flat table, which is grown as bigger numbers get spoken.
+/// Anything larger (only possible through the starting numbers, or after this many turns) goes
+/// into a map instead, so a single huge starting number doesn't allocate gigabytes.
+const MAX_TABLE_SIZE: usize = 1 << 26;
+
+/// The last turn each number was spoken on. Turns are counted from 1, so 0 means the number
+/// hasn't been spoken yet.
+struct LastSeenTable {
+    table: Vec<u32>,
+    large_numbers: HashMap<u32, u32>,
+}
+
+impl LastSeenTable {
+    pub fn new(largest_number: u32) -> LastSeenTable {
+        let table_size = (largest_number as usize + 1).clamp(1024, MAX_TABLE_SIZE);
+
+        LastSeenTable {
+            table: vec![0; table_size],
+            large_numbers: HashMap::new(),
+        }
+    }
+
+    /// Makes room for every number below `size` up front, saving growing the table along the way.
+    pub fn reserve(&mut self, size: usize) {
+        let table_size = size.min(MAX_TABLE_SIZE);
+        if table_size > self.table.len() {
+            self.table.resize(table_size, 0);
+        }
+    }
+
+    /// Records that `number` was spoken on `turn`, returning the turn it was spoken on before that.
+    pub fn record(&mut self, number: u32, turn: u32) -> u32 {
+        let index = number as usize;
+        if index >= self.table.len() && index < MAX_TABLE_SIZE {
+            self.reserve((index + 1).next_power_of_two());
+        }
+
+        match self.table.get_mut(index) {
+            Some(last_seen) => mem::replace(last_seen, turn),
+            None => self.large_numbers.insert(number, turn).unwrap_or(0),
+        }
+    }
+}
+
+/// Plays the elves' memory game, also known as Van Eck's sequence.
 struct MemoryGame {
-    // The number and the last 2 times it was spoken
-    spoken_numbers: HashMap<u128, Vec<u128>>,
-    last_spoken_number: u128,
-    turn: u128,
-    input: Vec<u128>,
+    starting_numbers: Vec<u32>,
+    // Doesn't include the turn of `last_spoken_number` yet
+    last_seen: LastSeenTable,
+    last_spoken_number: u32,
+    turn: u32,
 }
 
 impl MemoryGame {
-    pub fn new(input: &[u128]) -> MemoryGame {
+    pub fn new(input: &[u32]) -> MemoryGame {
+        let largest_starting_number = input.iter().copied().max().unwrap_or(0);
+
         MemoryGame {
-            spoken_numbers: HashMap::new(),
-            last_spoken_number: 0, // Doesn't count since we will update this with the first input number when it is spoken in the first turn
+            starting_numbers: Vec::from(input),
+            last_seen: LastSeenTable::new(largest_starting_number),
+            last_spoken_number: 0,
             turn: 0,
-            input: Vec::from(input),
         }
     }
 
-    pub fn next_number(&mut self) -> u128 {
+    /// Plays one turn and returns the number spoken on it. With no starting numbers the game
+    /// opens with 0.
+    pub fn next_number(&mut self) -> u32 {
         self.turn += 1;
-        let turn = self.turn as usize;
+        let turn = self.turn;
 
-        let next_number: u128;
-
-        if turn <= self.input.len() {
-            next_number = self.input[turn - 1];
-        } else {
-            let previous_turns_for_last_spoken_number = self.spoken_numbers.get(&self.last_spoken_number);
-            match previous_turns_for_last_spoken_number {
-                None => {
-                    panic!("This can't happen");
-                }
-                Some(previous_turns) => {
-                    if previous_turns.len() == 1 {
-                        next_number = 0;
-                    } else {
-                        next_number = previous_turns[1] - previous_turns[0];
-                    }
-                }
+        // The previous number only gets recorded now, since we need to know when it was spoken
+        // before that to work out this turn's number
+        let age = if turn > 1 {
+            let previous_turn = turn - 1;
+            match self.last_seen.record(self.last_spoken_number, previous_turn) {
+                0 => 0,
+                spoken_on => previous_turn - spoken_on,
             }
-        }
+        } else {
+            0
+        };
 
-        let next_number_spoken_turns = self.spoken_numbers.get_mut(&next_number);
-        match next_number_spoken_turns {
-            None => {
-                self.spoken_numbers.insert(
-                    next_number,
-                    vec![self.turn]
-                );
-            }
-            Some(turns) => {
-                if turns.len() == 1 {
-                    turns.push(self.turn)
-                } else {
-                    turns[0] = turns[1];
-                    turns[1] = self.turn;
-                }
-            }
-        }
+        let next_number = match self.starting_numbers.get(turn as usize - 1) {
+            Some(&starting_number) => starting_number,
+            None => age,
+        };
 
-        // println!("Turn: {}; Next Number: {}; Record: {:?}", turn, next_number, self.spoken_numbers.get(&next_number).unwrap());
         self.last_spoken_number = next_number;
         next_number
     }
 
-    pub fn nth(&mut self, n: u128) -> u128 {
+    /// Plays until turn `n` and returns the number spoken on it. Turns already played can't be
+    /// revisited, so asking for one of those plays the next turn instead.
+    pub fn number_spoken_on_turn(&mut self, n: u32) -> u32 {
+        // No number spoken after the starting ones can be bigger than the number of turns
+        self.last_seen.reserve(n as usize);
+
         loop {
-            if self.turn == n - 1 {
-                return self.next_number();
-            } else {
-                self.next_number();
+            let next_number = self.next_number();
+            if self.turn >= n {
+                return next_number;
             }
         }
     }
 }
 
+/// The game is infinite, so this only runs out once the turn counter would overflow.
+impl Iterator for MemoryGame {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        if self.turn == u32::MAX {
+            None
+        } else {
+            Some(self.next_number())
+        }
+    }
+}
+
+fn parse_starting_numbers(numbers: &str) -> Result<Vec<u32>, String> {
+    numbers.split(',')
+        .map(|number| {
+            number.trim().parse()
+                .map_err(|_| format!("`{}` is not a valid starting number", number))
+        })
+        .collect()
+}
+
+#[derive(PartialEq, Debug)]
+struct Options {
+    starting_numbers: Vec<u32>,
+    turns: Vec<u32>,
+    analyse: bool,
+    csv_directory: Option<String>,
+}
+
+/// `--analyse` prints statistics about the game up to the last turn asked for, and `--csv` also
+/// writes them out.
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut starting_numbers = None;
+    let mut turns = vec![];
+    let mut analyse = false;
+    let mut csv_directory = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match &arg[..] {
+            "--analyse" => analyse = true,
+            "--csv" => {
+                analyse = true;
+                csv_directory = Some(args.next().cloned().ok_or("--csv expects a directory")?);
+            }
+            _ if starting_numbers.is_none() => starting_numbers = Some(parse_starting_numbers(arg)?),
+            _ => {
+                let turn = arg.parse().ok()
+                    .filter(|turn| *turn > 0)
+                    .ok_or_else(|| format!("`{}` is not a valid turn", arg))?;
+                turns.push(turn);
+            }
+        }
+    }
+
+    let starting_numbers = starting_numbers.ok_or("Missing the starting numbers")?;
+    if turns.is_empty() {
+        turns = vec![2020, 30_000_000];
+    }
+    Ok(Options { starting_numbers, turns, analyse, csv_directory })
+}
+
 fn main() {
-    let mut game = MemoryGame::new(&[11, 0, 1, 10, 5, 19]);
-    println!("Part 1: {}", game.nth(2020));
-    println!("Part 2: {}", game.nth(30_000_000));
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}\n{}", error, USAGE);
+            process::exit(1);
+        }
+    };
+
+    for turn in options.turns.iter() {
+        let mut game = MemoryGame::new(&options.starting_numbers);
+        println!("Turn {}: {}", turn, game.number_spoken_on_turn(*turn));
+    }
+
+    if options.analyse {
+        let turns = options.turns.iter().copied().max().unwrap();
+        let analysis = Analysis::new(&options.starting_numbers, turns);
+
+        println!("Over {} turns:", turns);
+        println!("  Distinct numbers: {}", analysis.distinct_numbers());
+        println!("  Largest number: {}", analysis.largest_number);
+        println!("  Zeros: {} ({:.4}%)", analysis.zeros, analysis.zero_frequency() * 100.0);
+        if let Some((gap, count)) = analysis.gaps().max_by_key(|(_, count)| *count) {
+            println!("  Most common gap: {} ({} times)", gap, count);
+        }
+
+        if let Some(directory) = options.csv_directory {
+            if let Err(error) = analysis.write_csv(Path::new(&directory)) {
+                eprintln!("Writing the CSV files failed: {}", error);
+                process::exit(1);
+            }
+        }
+    }
 }
@@ -1,4 +1,5 @@
-use crate::*;
+use crate::{MemoryGame, Options, parse_args};
+use crate::analytics::Analysis;
 
 #[test]
 fn test_next_number() {
@@ -12,49 +13,134 @@ fn test_next_number() {
 }
 
 #[test]
-fn test_nth() {
+fn test_number_spoken_on_turn() {
     let mut game = MemoryGame::new(&[0, 3, 6]);
-    assert_eq!(game.nth(2020), 436);
+    assert_eq!(game.number_spoken_on_turn(2020), 436);
 
     let mut game = MemoryGame::new(&[1, 3, 2]);
-    assert_eq!(game.nth(2020), 1);
+    assert_eq!(game.number_spoken_on_turn(2020), 1);
 
     let mut game = MemoryGame::new(&[2, 1, 3]);
-    assert_eq!(game.nth(2020), 10);
+    assert_eq!(game.number_spoken_on_turn(2020), 10);
 
     let mut game = MemoryGame::new(&[1, 2, 3]);
-    assert_eq!(game.nth(2020), 27);
+    assert_eq!(game.number_spoken_on_turn(2020), 27);
 
     let mut game = MemoryGame::new(&[2, 3, 1]);
-    assert_eq!(game.nth(2020), 78);
+    assert_eq!(game.number_spoken_on_turn(2020), 78);
 
     let mut game = MemoryGame::new(&[3, 2, 1]);
-    assert_eq!(game.nth(2020), 438);
+    assert_eq!(game.number_spoken_on_turn(2020), 438);
 
     let mut game = MemoryGame::new(&[3, 1, 2]);
-    assert_eq!(game.nth(2020), 1836);
+    assert_eq!(game.number_spoken_on_turn(2020), 1836);
 }
 
 #[test]
-fn test_part_2_nth() {
+fn test_part_2_number_spoken_on_turn() {
     let mut game = MemoryGame::new(&[0, 3, 6]);
-    assert_eq!(game.nth(30_000_000), 175594);
+    assert_eq!(game.number_spoken_on_turn(30_000_000), 175594);
 
     let mut game = MemoryGame::new(&[1, 3, 2]);
-    assert_eq!(game.nth(30000000), 2578);
+    assert_eq!(game.number_spoken_on_turn(30000000), 2578);
 
     let mut game = MemoryGame::new(&[2, 1, 3]);
-    assert_eq!(game.nth(30000000), 3544142);
+    assert_eq!(game.number_spoken_on_turn(30000000), 3544142);
 
     let mut game = MemoryGame::new(&[1, 2, 3]);
-    assert_eq!(game.nth(30000000), 261214);
+    assert_eq!(game.number_spoken_on_turn(30000000), 261214);
 
     let mut game = MemoryGame::new(&[2, 3, 1]);
-    assert_eq!(game.nth(30000000), 6895259);
+    assert_eq!(game.number_spoken_on_turn(30000000), 6895259);
 
     let mut game = MemoryGame::new(&[3, 2, 1]);
-    assert_eq!(game.nth(30000000), 18);
+    assert_eq!(game.number_spoken_on_turn(30000000), 18);
 
     let mut game = MemoryGame::new(&[3, 1, 2]);
-    assert_eq!(game.nth(30000000), 362);
-}
\ No newline at end of file
+    assert_eq!(game.number_spoken_on_turn(30000000), 362);
+}
+
+#[test]
+fn test_spoken_numbers_iterator() {
+    let game = MemoryGame::new(&[0, 3, 6]);
+    let spoken: Vec<u32> = game.take(10).collect();
+    assert_eq!(spoken, vec![0, 3, 6, 0, 3, 3, 1, 0, 4, 0]);
+
+    // 2020 is the 2019th number after skipping the first one
+    let mut game = MemoryGame::new(&[0, 3, 6]);
+    assert_eq!(game.number_spoken_on_turn(1), 0);
+    assert_eq!(game.nth(2018), Some(436));
+}
+
+#[test]
+fn test_repeated_and_large_starting_numbers() {
+    let spoken: Vec<u32> = MemoryGame::new(&[1, 1]).take(6).collect();
+    assert_eq!(spoken, vec![1, 1, 1, 1, 1, 1]);
+
+    // Large numbers don't fit in the table, but play the same as small ones
+    let large = 4_000_000_000;
+    let spoken: Vec<u32> = MemoryGame::new(&[large, 3, large]).take(8).collect();
+    assert_eq!(spoken, vec![large, 3, large, 2, 0, 0, 1, 0]);
+
+    let spoken: Vec<u32> = MemoryGame::new(&[]).take(4).collect();
+    assert_eq!(spoken, vec![0, 0, 1, 0]);
+}
+
+#[test]
+fn test_parse_args() {
+    let args = vec![String::from("0,3, 6")];
+    assert_eq!(parse_args(&args), Ok(Options {
+        starting_numbers: vec![0, 3, 6],
+        turns: vec![2020, 30_000_000],
+        analyse: false,
+        csv_directory: None,
+    }));
+
+    let args: Vec<String> = ["0,3,6", "10", "--csv", "out"].iter().map(|arg| arg.to_string()).collect();
+    assert_eq!(parse_args(&args), Ok(Options {
+        starting_numbers: vec![0, 3, 6],
+        turns: vec![10],
+        analyse: true,
+        csv_directory: Some(String::from("out")),
+    }));
+
+    assert!(parse_args(&[]).is_err());
+    assert!(parse_args(&[String::from("--analyse")]).is_err());
+    assert!(parse_args(&[String::from("0,x")]).is_err());
+    assert!(parse_args(&[String::from("0"), String::from("0")]).is_err());
+    assert!(parse_args(&[String::from("0"), String::from("--csv")]).is_err());
+}
+
+#[test]
+fn test_analysis() {
+    // 0, 3, 6, 0, 3, 3, 1, 0, 4, 0
+    let analysis = Analysis::new(&[0, 3, 6], 10);
+
+    assert_eq!(analysis.distinct_numbers(), 5);
+    assert_eq!(analysis.largest_number, 6);
+    assert_eq!(analysis.zeros, 4);
+    assert_eq!(analysis.zero_frequency(), 0.4);
+    assert_eq!(analysis.first_appearances, vec![(0, 1), (3, 2), (6, 3), (1, 7), (4, 9)]);
+
+    let gaps: Vec<(u32, u32)> = analysis.gaps().collect();
+    assert_eq!(gaps, vec![(1, 1), (2, 1), (3, 2), (4, 1)]);
+}
+
+#[test]
+fn test_analysis_csv() {
+    let analysis = Analysis::new(&[0, 3, 6], 10);
+
+    let mut summary = vec![];
+    analysis.write_summary_csv(&mut summary).unwrap();
+    assert_eq!(String::from_utf8(summary).unwrap(),
+               "statistic,value\nturns,10\ndistinct_numbers,5\nlargest_number,6\nzeros,4\nzero_frequency,0.4\n");
+
+    let mut gaps = vec![];
+    analysis.write_gaps_csv(&mut gaps).unwrap();
+    assert_eq!(String::from_utf8(gaps).unwrap(), "gap,count\n1,1\n2,1\n3,2\n4,1\n");
+
+    let mut first_appearances = vec![];
+    analysis.write_first_appearances_csv(&mut first_appearances).unwrap();
+    assert_eq!(String::from_utf8(first_appearances).unwrap(),
+               "number,first_turn\n0,1\n3,2\n6,3\n1,7\n4,9\n");
+}
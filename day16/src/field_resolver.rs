@@ -0,0 +1,320 @@
+use crate::{PossibleTicket, TicketFormat};
+use std::collections::VecDeque;
+
+/// For each field (by its index in the ticket format), the column of the ticket it's in.
+pub type Assignment = Vec<usize>;
+
+/// Which fields can be in which columns, given the values seen in each column of the tickets.
+pub struct CompatibilityMatrix {
+    fields: usize,
+    columns: usize,
+    compatible: Vec<bool>,
+}
+
+impl CompatibilityMatrix {
+    /// `tickets` should only be the valid ones, otherwise a value no field allows rules out
+    /// every field for its column. Every ticket has a column for each field, so without any
+    /// tickets every field fits every column.
+    pub fn new(format: &TicketFormat, tickets: &[PossibleTicket]) -> CompatibilityMatrix {
+        let fields = format.fields.len();
+        let columns = fields;
+        let mut compatible = vec![true; fields * columns];
+
+        for ticket in tickets.iter() {
+            for (column, value) in ticket.field_values.iter().enumerate() {
+                for (field, field_config) in format.fields.iter().enumerate() {
+                    if !field_config.is_valid_value(*value) {
+                        compatible[field * columns + column] = false;
+                    }
+                }
+            }
+        }
+
+        CompatibilityMatrix { fields, columns, compatible }
+    }
+
+    pub fn is_compatible(&self, field: usize, column: usize) -> bool {
+        self.compatible[field * self.columns + column]
+    }
+
+    pub fn columns_for_field(&self, field: usize) -> Vec<usize> {
+        (0..self.columns).filter(|column| self.is_compatible(field, *column)).collect()
+    }
+}
+
+/// A set of fields that can't all be given a column, because between them they fit in fewer
+/// columns than there are fields. No field can be left out without the conflict going away, and
+/// no other conflict has fewer fields.
+#[derive(Debug, PartialEq)]
+pub struct Conflict {
+    pub fields: Vec<usize>,
+    pub columns: Vec<usize>,
+}
+
+impl Conflict {
+    pub fn describe(&self, format: &TicketFormat) -> String {
+        let names: Vec<&str> = self.fields.iter()
+            .map(|field| &format.fields[*field].name[..])
+            .collect();
+
+        format!("{} fields ({}) only fit in {} columns ({:?})",
+                self.fields.len(), names.join(", "), self.columns.len(), self.columns)
+    }
+}
+
+pub struct FieldResolver {
+    matrix: CompatibilityMatrix,
+}
+
+impl FieldResolver {
+    pub fn new(matrix: CompatibilityMatrix) -> FieldResolver {
+        FieldResolver { matrix }
+    }
+
+    /// Finds every way of giving each field its own column, stopping after `max_assignments`.
+    /// Fields that only fit one column (or columns only one field fits) are settled first, and
+    /// whatever is left is searched, checking with a maximum matching that each choice still
+    /// leaves a way to place the remaining fields.
+    pub fn resolve(&self, max_assignments: usize) -> Result<Vec<Assignment>, Conflict> {
+        let mut candidates: Vec<Vec<usize>> = (0..self.matrix.fields)
+            .map(|field| self.matrix.columns_for_field(field))
+            .collect();
+
+        let settled = self.propagate(&mut candidates);
+        if settled.is_none() || !has_complete_matching(&candidates, self.matrix.columns) {
+            return Err(self.find_conflict());
+        }
+
+        let mut assignments = vec![];
+        let mut assignment = vec![None; self.matrix.fields];
+        let mut used_columns = vec![false; self.matrix.columns];
+        search(&mut candidates, &mut assignment, &mut used_columns, max_assignments, &mut assignments);
+
+        Ok(assignments)
+    }
+
+    /// Settles fields with only one possible column, and (when there are exactly as many fields
+    /// as columns) columns with only one possible field, until nothing changes. Returns `None`
+    /// if a field is left without any columns.
+    fn propagate(&self, candidates: &mut [Vec<usize>]) -> Option<()> {
+        let square = self.matrix.fields == self.matrix.columns;
+
+        loop {
+            let mut changed = false;
+
+            for field in 0..candidates.len() {
+                match candidates[field].len() {
+                    0 => return None,
+                    1 => {
+                        let column = candidates[field][0];
+                        for (other_field, other_candidates) in candidates.iter_mut().enumerate() {
+                            if other_field != field && other_candidates.contains(&column) {
+                                other_candidates.retain(|c| *c != column);
+                                changed = true;
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            if square {
+                for column in 0..self.matrix.columns {
+                    let fields: Vec<usize> = (0..candidates.len())
+                        .filter(|field| candidates[*field].contains(&column))
+                        .collect();
+
+                    match fields.len() {
+                        0 => return None,
+                        1 if candidates[fields[0]].len() > 1 => {
+                            candidates[fields[0]] = vec![column];
+                            changed = true;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+
+            if !changed {
+                return Some(());
+            }
+        }
+    }
+
+    /// Finds the smallest set of fields that stop a complete assignment. A maximum matching
+    /// leaves some fields out, and everything reachable from one of them along alternating paths
+    /// fits in one column fewer than it has fields (Hall's theorem). Any conflict has to hold a
+    /// field the matching leaves out, and then everything reachable from it, so these are the
+    /// only conflicts no field can be dropped from, and the smallest of them is the answer.
+    fn find_conflict(&self) -> Conflict {
+        let candidates: Vec<Vec<usize>> = (0..self.matrix.fields)
+            .map(|field| self.matrix.columns_for_field(field))
+            .collect();
+
+        let matching = maximum_matching(&candidates, self.matrix.columns);
+        let mut field_of_column = vec![None; self.matrix.columns];
+        for (field, column) in matching.iter().enumerate() {
+            if let Some(column) = column {
+                field_of_column[*column] = Some(field);
+            }
+        }
+
+        let fields = (0..self.matrix.fields)
+            .filter(|field| matching[*field].is_none())
+            .map(|unmatched_field| reachable_fields(unmatched_field, &candidates, &field_of_column))
+            .min_by_key(|fields| fields.len())
+            .expect("A conflict only exists if some field can't be matched");
+
+        let columns = self.columns_for_fields(&fields);
+        Conflict { fields, columns }
+    }
+
+    fn columns_for_fields(&self, fields: &[usize]) -> Vec<usize> {
+        (0..self.matrix.columns)
+            .filter(|column| fields.iter().any(|field| self.matrix.is_compatible(*field, *column)))
+            .collect()
+    }
+}
+
+/// The fields reachable from `unmatched_field` by alternating between a candidate column and the
+/// field matched to it, including `unmatched_field` itself, in order.
+fn reachable_fields(unmatched_field: usize, candidates: &[Vec<usize>], field_of_column: &[Option<usize>]) -> Vec<usize> {
+    let mut reached_fields = vec![false; candidates.len()];
+    let mut reached_columns = vec![false; field_of_column.len()];
+    let mut queue = VecDeque::new();
+    reached_fields[unmatched_field] = true;
+    queue.push_back(unmatched_field);
+
+    while let Some(field) = queue.pop_front() {
+        for column in candidates[field].iter() {
+            if reached_columns[*column] {
+                continue;
+            }
+            reached_columns[*column] = true;
+
+            // The matching is maximum, so every column we reach this way is taken
+            let matched_field = field_of_column[*column].unwrap();
+            if !reached_fields[matched_field] {
+                reached_fields[matched_field] = true;
+                queue.push_back(matched_field);
+            }
+        }
+    }
+
+    (0..candidates.len()).filter(|field| reached_fields[*field]).collect()
+}
+
+/// Tries every column for the field with the fewest options left, skipping any choice that
+/// leaves the remaining fields without a complete matching, so every branch ends in an assignment.
+fn search(candidates: &mut Vec<Vec<usize>>, assignment: &mut [Option<usize>], used_columns: &mut [bool],
+          max_assignments: usize, assignments: &mut Vec<Assignment>) {
+    if assignments.len() >= max_assignments {
+        return;
+    }
+
+    let next_field = (0..assignment.len())
+        .filter(|field| assignment[*field].is_none())
+        .min_by_key(|field| candidates[*field].len());
+
+    let field = match next_field {
+        Some(field) => field,
+        None => {
+            assignments.push(assignment.iter().map(|column| column.unwrap()).collect());
+            return;
+        }
+    };
+
+    let options: Vec<usize> = candidates[field].iter().copied().filter(|column| !used_columns[*column]).collect();
+    for column in options {
+        let saved = candidates.clone();
+        assignment[field] = Some(column);
+        used_columns[column] = true;
+        for (other_field, other_candidates) in candidates.iter_mut().enumerate() {
+            if other_field == field {
+                *other_candidates = vec![column];
+            } else {
+                other_candidates.retain(|c| *c != column);
+            }
+        }
+
+        if has_complete_matching(candidates, used_columns.len()) {
+            search(candidates, assignment, used_columns, max_assignments, assignments);
+        }
+
+        *candidates = saved;
+        assignment[field] = None;
+        used_columns[column] = false;
+    }
+}
+
+fn has_complete_matching(candidates: &[Vec<usize>], columns: usize) -> bool {
+    maximum_matching(candidates, columns).iter().all(|column| column.is_some())
+}
+
+/// Hopcroft–Karp: the column each field is matched to, in a maximum matching of fields to their
+/// candidate columns.
+fn maximum_matching(candidates: &[Vec<usize>], columns: usize) -> Vec<Option<usize>> {
+    let mut column_of_field: Vec<Option<usize>> = vec![None; candidates.len()];
+    let mut field_of_column: Vec<Option<usize>> = vec![None; columns];
+    let mut layer = vec![0usize; candidates.len()];
+
+    loop {
+        // Layer the free fields and everything reachable from them along alternating paths
+        let mut queue = VecDeque::new();
+        let mut found_augmenting_path = false;
+        for field in 0..candidates.len() {
+            if column_of_field[field].is_none() {
+                layer[field] = 0;
+                queue.push_back(field);
+            } else {
+                layer[field] = usize::MAX;
+            }
+        }
+
+        while let Some(field) = queue.pop_front() {
+            for column in candidates[field].iter() {
+                match field_of_column[*column] {
+                    None => found_augmenting_path = true,
+                    Some(next_field) if layer[next_field] == usize::MAX => {
+                        layer[next_field] = layer[field] + 1;
+                        queue.push_back(next_field);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        if !found_augmenting_path {
+            return column_of_field;
+        }
+
+        for field in 0..candidates.len() {
+            if column_of_field[field].is_none() {
+                augment(field, candidates, &mut layer, &mut column_of_field, &mut field_of_column);
+            }
+        }
+    }
+}
+
+fn augment(field: usize, candidates: &[Vec<usize>], layer: &mut [usize],
+           column_of_field: &mut [Option<usize>], field_of_column: &mut [Option<usize>]) -> bool {
+    for column in candidates[field].iter() {
+        let can_augment = match field_of_column[*column] {
+            None => true,
+            Some(next_field) => {
+                layer[next_field] == layer[field] + 1
+                    && augment(next_field, candidates, layer, column_of_field, field_of_column)
+            }
+        };
+
+        if can_augment {
+            column_of_field[field] = Some(*column);
+            field_of_column[*column] = Some(field);
+            return true;
+        }
+    }
+
+    // Nothing more to find through this field in this phase
+    layer[field] = usize::MAX;
+    false
+}
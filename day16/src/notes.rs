@@ -0,0 +1,107 @@
+use crate::{FieldConfig, PossibleTicket, TicketFormat};
+use core::fmt;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, PartialEq)]
+pub enum NoteError {
+    InvalidRule(String),
+    InvalidRange(String),
+    InvalidValue(String),
+    UnexpectedLine(String),
+    MissingSection(&'static str),
+    WrongNumberOfValues { expected: usize, found: usize },
+}
+
+impl Display for NoteError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            NoteError::InvalidRule(rule) => write!(f, "Expected a rule like `name: 1-3 or 5-7`, found `{}`", rule),
+            NoteError::InvalidRange(range) => write!(f, "`{}` is not a valid range", range),
+            NoteError::InvalidValue(value) => write!(f, "`{}` is not a valid ticket value", value),
+            NoteError::UnexpectedLine(line) => write!(f, "Didn't expect `{}` here", line),
+            NoteError::MissingSection(section) => write!(f, "Missing the `{}` section", section),
+            NoteError::WrongNumberOfValues { expected, found } => {
+                write!(f, "Tickets have {} values, but this one has {}", expected, found)
+            }
+        }
+    }
+}
+
+/// A problem with the notes, and the (1-based) line it's on. Missing sections are reported on the
+/// line after the last one.
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub error: NoteError,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Line {}: {}", self.line, self.error)
+    }
+}
+
+const YOUR_TICKET: &str = "your ticket:";
+const NEARBY_TICKETS: &str = "nearby tickets:";
+
+/// The notes we took about the tickets: the rules for each field, our own ticket and the ones
+/// nearby. Each section is separated by blank lines, and the tickets come after a header.
+#[derive(Debug)]
+pub struct Notes {
+    pub format: TicketFormat,
+    pub your_ticket: PossibleTicket,
+    pub nearby_tickets: Vec<PossibleTicket>,
+}
+
+impl Notes {
+    pub fn parse(input: &str) -> Result<Notes, ParseError> {
+        let mut lines = input.lines()
+            .map(str::trim)
+            .enumerate()
+            .map(|(index, line)| (index + 1, line))
+            .filter(|(_, line)| !line.is_empty())
+            .peekable();
+        let end_of_input = input.lines().count() + 1;
+
+        let mut fields = vec![];
+        while let Some((line_number, line)) = lines.next_if(|(_, line)| *line != YOUR_TICKET) {
+            let field = FieldConfig::from_input(line)
+                .map_err(|error| ParseError { line: line_number, error })?;
+            fields.push(field);
+        }
+        let format = TicketFormat { fields };
+
+        let missing = |section| ParseError { line: end_of_input, error: NoteError::MissingSection(section) };
+        lines.next().ok_or_else(|| missing(YOUR_TICKET))?;
+        let your_ticket = match lines.next() {
+            Some((_, NEARBY_TICKETS)) | None => return Err(missing(YOUR_TICKET)),
+            Some((line_number, line)) => Notes::parse_ticket(&format, line_number, line)?,
+        };
+
+        match lines.next() {
+            Some((_, NEARBY_TICKETS)) => {}
+            Some((line_number, line)) => {
+                return Err(ParseError { line: line_number, error: NoteError::UnexpectedLine(String::from(line)) });
+            }
+            None => return Err(missing(NEARBY_TICKETS)),
+        }
+
+        let nearby_tickets = lines
+            .map(|(line_number, line)| Notes::parse_ticket(&format, line_number, line))
+            .collect::<Result<Vec<PossibleTicket>, ParseError>>()?;
+
+        Ok(Notes { format, your_ticket, nearby_tickets })
+    }
+
+    fn parse_ticket(format: &TicketFormat, line_number: usize, line: &str) -> Result<PossibleTicket, ParseError> {
+        let ticket = PossibleTicket::from_input(line)
+            .map_err(|error| ParseError { line: line_number, error })?;
+
+        let (expected, found) = (format.fields.len(), ticket.field_values.len());
+        if expected != found {
+            return Err(ParseError { line: line_number, error: NoteError::WrongNumberOfValues { expected, found } });
+        }
+
+        Ok(ticket)
+    }
+}
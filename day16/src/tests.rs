@@ -1,27 +1,59 @@
-use crate::*;
+use crate::{FieldConfig, TicketFormat, PossibleTicket, scanning_error_rate, get_test_input, parse_args};
+use crate::field_resolver::{CompatibilityMatrix, Conflict, FieldResolver};
+use crate::notes::{NoteError, Notes, ParseError};
+use crate::validation_report::{InvalidValue, NearestRange, ValidationReport};
 
 #[test]
 fn test_field_config_from_input() {
     let input = "arrival location: 30-542 or 556-960";
-    let field_config = FieldConfig::from_input(input);
+    let field_config = FieldConfig::from_input(input).unwrap();
     assert_eq!(field_config.name, "arrival location");
 }
 
 #[test]
 fn test_field_config_is_valid_value() {
-    let field_config = FieldConfig::from_input("class: 1-3 or 5-7");
+    let field_config = FieldConfig::from_input("class: 1-3 or 5-7").unwrap();
     assert!(!field_config.is_valid_value(4));
     assert!(field_config.is_valid_value(6));
 }
 
 #[test]
-fn test_ticket_format_from_input() {
-    let input = vec![
-        String::from("class: 1-3 or 5-7"),
-        String::from("row: 6-11 or 33-44"),
-        String::from("seat: 13-40 or 45-50"),
-    ];
-    let ticket_format = TicketFormat::from_input(&input);
+fn test_field_config_with_any_number_of_ranges() {
+    let field_config = FieldConfig::from_input("zone: 5-5").unwrap();
+    assert_eq!(field_config.valid_ranges, vec![(5, 5)]);
+    assert!(field_config.is_valid_value(5));
+    assert!(!field_config.is_valid_value(6));
+
+    let field_config = FieldConfig::from_input("zone: 1-2 or 4-5 or 7-7 or 10-20").unwrap();
+    assert_eq!(field_config.valid_ranges.len(), 4);
+    assert!(field_config.is_valid_value(15));
+    assert!(!field_config.is_valid_value(3));
+    assert!(!field_config.is_valid_value(9));
+}
+
+#[test]
+fn test_field_config_errors() {
+    assert_eq!(FieldConfig::from_input("zone 1-3"), Err(NoteError::InvalidRule(String::from("zone 1-3"))));
+    assert_eq!(FieldConfig::from_input("zone: "), Err(NoteError::InvalidRule(String::from("zone: "))));
+    assert_eq!(FieldConfig::from_input("zone: 1-3 or 5"), Err(NoteError::InvalidRange(String::from("5"))));
+    assert_eq!(FieldConfig::from_input("zone: 1-3 or 7-5"), Err(NoteError::InvalidRange(String::from("7-5"))));
+    assert_eq!(FieldConfig::from_input("zone: 1-3 and 5-7"), Err(NoteError::InvalidRange(String::from("1-3 and 5-7"))));
+}
+
+/// Notes with the given rules and nearby tickets. Our own ticket is a copy of the first one.
+fn notes_with(rules: &[&str], tickets: &[&str]) -> Notes {
+    let notes = format!(
+        "{}\n\nyour ticket:\n{}\n\nnearby tickets:\n{}\n",
+        rules.join("\n"), tickets[0], tickets.join("\n")
+    );
+    Notes::parse(&notes).unwrap()
+}
+
+const SAMPLE_RULES: [&str; 3] = ["class: 1-3 or 5-7", "row: 6-11 or 33-44", "seat: 13-40 or 45-50"];
+
+#[test]
+fn test_ticket_format_from_notes() {
+    let ticket_format = notes_with(&SAMPLE_RULES, &["7,1,14"]).format;
 
     assert_eq!(ticket_format.fields[0].name, "class");
     assert_eq!(ticket_format.fields[0].valid_ranges[0].0, 1);
@@ -44,77 +76,258 @@ fn test_ticket_format_from_input() {
 
 #[test]
 fn test_possible_ticket_from_input() {
-    let possible_ticket = PossibleTicket::from_input("7,1,14");
+    let possible_ticket = PossibleTicket::from_input("7,1,14").unwrap();
     assert_eq!(possible_ticket.field_values, vec![7, 1, 14]);
 }
 
 #[test]
 fn test_possible_ticket_get_invalid_field_values() {
-    let input = vec![
-        String::from("class: 1-3 or 5-7"),
-        String::from("row: 6-11 or 33-44"),
-        String::from("seat: 13-40 or 45-50"),
-    ];
-    let ticket_format = TicketFormat::from_input(&input);
+    let ticket_format = notes_with(&SAMPLE_RULES, &["7,1,14"]).format;
 
-    let possible_ticket = PossibleTicket::from_input("7,3,47");
+    let possible_ticket = PossibleTicket::from_input("7,3,47").unwrap();
     assert_eq!(possible_ticket.get_invalid_field_values(&ticket_format).len(), 0);
 
-    let possible_ticket = PossibleTicket::from_input("40,4,50");
+    let possible_ticket = PossibleTicket::from_input("40,4,50").unwrap();
     assert_eq!(possible_ticket.get_invalid_field_values(&ticket_format), vec![4]);
 
-    let possible_ticket = PossibleTicket::from_input("55,2,20");
+    let possible_ticket = PossibleTicket::from_input("55,2,20").unwrap();
     assert_eq!(possible_ticket.get_invalid_field_values(&ticket_format), vec![55]);
 
-    let possible_ticket = PossibleTicket::from_input("38,6,12");
+    let possible_ticket = PossibleTicket::from_input("38,6,12").unwrap();
     assert_eq!(possible_ticket.get_invalid_field_values(&ticket_format), vec![12]);
 }
 
 #[test]
 fn test_scanning_error_rate() {
-    let input = vec![
-        String::from("class: 1-3 or 5-7"),
-        String::from("row: 6-11 or 33-44"),
-        String::from("seat: 13-40 or 45-50"),
-    ];
-    let ticket_format = TicketFormat::from_input(&input);
+    let ticket_format = notes_with(&SAMPLE_RULES, &["7,1,14"]).format;
     let tickets = vec![
-        PossibleTicket::from_input("7,3,47"),
-        PossibleTicket::from_input("40,4,50"),
-        PossibleTicket::from_input("55,2,20"),
-        PossibleTicket::from_input("38,6,12"),
+        PossibleTicket::from_input("7,3,47").unwrap(),
+        PossibleTicket::from_input("40,4,50").unwrap(),
+        PossibleTicket::from_input("55,2,20").unwrap(),
+        PossibleTicket::from_input("38,6,12").unwrap(),
     ];
 
     assert_eq!(scanning_error_rate(&ticket_format, &tickets), 71);
 }
 
 #[test]
-fn test_get_possible_fields_for_each_value_index() {
-    let (
-        mut ticket_format,
-        mut your_ticket,
-        mut nearby_tickets
-    ) = get_test_input();
+fn test_compatibility_matrix() {
+    let Notes { format: ticket_format, nearby_tickets, .. } = get_test_input();
 
-    let mut possible_field_mappings = vec![];
-    for ticket in nearby_tickets.iter() {
-        possible_field_mappings.push(
-            ticket.get_possible_fields_for_each_value_index(&ticket_format)
-        );
-    }
+    let matrix = CompatibilityMatrix::new(&ticket_format, &nearby_tickets);
+    assert_eq!(matrix.columns_for_field(0), vec![1, 2], "class");
+    assert_eq!(matrix.columns_for_field(1), vec![0, 1, 2], "row");
+    assert_eq!(matrix.columns_for_field(2), vec![2], "seat");
+    assert!(!matrix.is_compatible(2, 0));
+}
+
+#[test]
+fn test_resolve_fields() {
+    let Notes { format: ticket_format, nearby_tickets, .. } = get_test_input();
+
+    let resolver = FieldResolver::new(CompatibilityMatrix::new(&ticket_format, &nearby_tickets));
+    assert_eq!(resolver.resolve(10), Ok(vec![vec![1, 0, 2]]));
+}
+
+fn format_and_tickets(rules: &[&str], tickets: &[&str]) -> (TicketFormat, Vec<PossibleTicket>) {
+    let notes = notes_with(rules, tickets);
+    (notes.format, notes.nearby_tickets)
+}
+
+#[test]
+fn test_resolve_fields_without_singletons() {
+    // Every field fits two columns and every column fits two fields, so propagation alone
+    // can't settle anything
+    let (ticket_format, tickets) = format_and_tickets(
+        &["a: 1-2 or 3-4", "b: 3-4 or 5-6", "c: 1-2 or 5-6"],
+        &["1,3,5", "2,4,6"],
+    );
+
+    let resolver = FieldResolver::new(CompatibilityMatrix::new(&ticket_format, &tickets));
+    let mut assignments = resolver.resolve(10).unwrap();
+    assignments.sort();
+    assert_eq!(assignments, vec![vec![0, 1, 2], vec![1, 2, 0]]);
+
+    assert_eq!(resolver.resolve(1).unwrap().len(), 1);
+}
+
+#[test]
+fn test_resolve_fields_conflict() {
+    // a and b only fit column 0, while c fits anywhere
+    let (ticket_format, tickets) = format_and_tickets(
+        &["a: 1-1 or 100-100", "b: 1-1 or 100-100", "c: 1-3 or 100-100"],
+        &["1,2,3"],
+    );
+
+    let resolver = FieldResolver::new(CompatibilityMatrix::new(&ticket_format, &tickets));
+    let conflict = resolver.resolve(10).unwrap_err();
+    assert_eq!(conflict, Conflict { fields: vec![0, 1], columns: vec![0] });
+    assert_eq!(conflict.describe(&ticket_format), "2 fields (a, b) only fit in 1 columns ([0])");
+
+    // A field no column fits is a conflict on its own
+    let (ticket_format, tickets) = format_and_tickets(
+        &["a: 1-3 or 100-100", "b: 5-5 or 100-100", "c: 1-3 or 100-100"],
+        &["1,2,3"],
+    );
+
+    let resolver = FieldResolver::new(CompatibilityMatrix::new(&ticket_format, &tickets));
+    assert_eq!(resolver.resolve(10), Err(Conflict { fields: vec![1], columns: vec![] }));
+
+    // a, b and c share columns 1 and 2, and d and e share column 0. The matching leaves out one
+    // field of each group, and the pair is the smaller conflict.
+    let (ticket_format, tickets) = format_and_tickets(
+        &["a: 2-3", "b: 2-3", "c: 2-3", "d: 1-1", "e: 1-1"],
+        &["1,2,3,4,5"],
+    );
+
+    let resolver = FieldResolver::new(CompatibilityMatrix::new(&ticket_format, &tickets));
+    assert_eq!(resolver.resolve(10), Err(Conflict { fields: vec![3, 4], columns: vec![0] }));
+}
+
+#[test]
+fn test_resolve_fields_without_valid_tickets() {
+    // Nothing rules any column out, so the fields can go anywhere
+    let (ticket_format, _) = format_and_tickets(&["a: 1-2", "b: 3-4"], &["1,3"]);
+
+    let resolver = FieldResolver::new(CompatibilityMatrix::new(&ticket_format, &[]));
+    let mut assignments = resolver.resolve(10).unwrap();
+    assignments.sort();
+    assert_eq!(assignments, vec![vec![0, 1], vec![1, 0]]);
+}
+
+#[test]
+fn test_parse_notes() {
+    let notes = get_test_input();
+
+    assert_eq!(notes.format.fields.len(), 3);
+    assert_eq!(notes.format.fields[2].name, "seat");
+    assert_eq!(notes.your_ticket.field_values, vec![11, 12, 13]);
+    assert_eq!(notes.nearby_tickets.len(), 3);
+    assert_eq!(notes.nearby_tickets[1].field_values, vec![15, 1, 5]);
+}
+
+#[test]
+fn test_parse_notes_errors() {
+    let error = |line, error| Err(ParseError { line, error });
+
+    let notes = "class: 1-3 or 5-7\nrow 6-11\n\nyour ticket:\n7\n\nnearby tickets:\n1\n";
+    assert_eq!(Notes::parse(notes).map(|_| ()), error(2, NoteError::InvalidRule(String::from("row 6-11"))));
+
+    let notes = "class: 1-3 or 5-7\n\nyour ticket:\n7\n\nnearby tickets:\n1\nx\n";
+    assert_eq!(Notes::parse(notes).map(|_| ()), error(8, NoteError::InvalidValue(String::from("x"))));
+
+    let notes = "class: 1-3 or 5-7\n\nyour ticket:\n7\n\nnearby tickets:\n1\n1,2\n";
+    assert_eq!(Notes::parse(notes).map(|_| ()), error(8, NoteError::WrongNumberOfValues { expected: 1, found: 2 }));
+
+    let notes = "class: 1-3 or 5-7\n\nyour ticket:\n7\n8\n";
+    assert_eq!(Notes::parse(notes).map(|_| ()), error(5, NoteError::UnexpectedLine(String::from("8"))));
+
+    let notes = "class: 1-3 or 5-7\n\nyour ticket:\n7\n";
+    assert_eq!(Notes::parse(notes).map(|_| ()), error(5, NoteError::MissingSection("nearby tickets:")));
+
+    let notes = "class: 1-3 or 5-7\n";
+    let parsed = Notes::parse(notes).map(|_| ());
+    assert_eq!(parsed, error(2, NoteError::MissingSection("your ticket:")));
+    assert_eq!(parsed.unwrap_err().to_string(), "Line 2: Missing the `your ticket:` section");
+
+    // The header on its own isn't enough, the section needs our ticket too
+    let notes = "class: 1-3 or 5-7\n\nyour ticket:\n\nnearby tickets:\n1\n";
+    assert_eq!(Notes::parse(notes).map(|_| ()), error(7, NoteError::MissingSection("your ticket:")));
+}
+
+fn example_report() -> ValidationReport {
+    // One more rule than there are columns, so the notes parser would turn these tickets down
+    let rules = ["class: 1-3 or 5-7", "row: 6-11 or 33-44", "seat: 13-40 or 45-50", "zone: 100-200"];
+    let ticket_format = TicketFormat {
+        fields: rules.iter().map(|rule| FieldConfig::from_input(rule).unwrap()).collect()
+    };
+    let tickets: Vec<PossibleTicket> = ["7,3,47", "40,4,50", "55,2,20", "38,6,12"].iter()
+        .map(|ticket| PossibleTicket::from_input(ticket).unwrap())
+        .collect();
+    ValidationReport::new(&ticket_format, &tickets)
+}
+
+#[test]
+fn test_validation_report() {
+    let report = example_report();
 
-    let mut i = possible_field_mappings.iter();
-    let mut combined_possible_field_mappings = i.next().unwrap().clone();
-    combined_possible_field_mappings = i.fold(combined_possible_field_mappings, |acc, x| {
-        let mut output = vec![];
-        for (a, b) in x.iter().zip(acc) {
-            output.push(
-                b.intersection(a).cloned().collect()
-            )
-        }
-
-        return output;
-    });
-
-    println!("{:?}", combined_possible_field_mappings);
-}
\ No newline at end of file
+    assert!(report.tickets[0].is_valid());
+    assert_eq!(report.tickets[1].invalid_values, vec![InvalidValue {
+        column: 1,
+        value: 4,
+        nearest_range: Some(NearestRange { field: String::from("class"), range: (1, 3), distance: 1 }),
+    }]);
+    assert_eq!(report.tickets[2].invalid_values[0].column, 0);
+    assert_eq!(report.tickets[2].invalid_values[0].nearest_range,
+               Some(NearestRange { field: String::from("seat"), range: (45, 50), distance: 5 }));
+    assert_eq!(report.tickets[3].invalid_values[0].column, 2);
+
+    let invalid_tickets: Vec<usize> = report.invalid_tickets().map(|ticket| ticket.ticket).collect();
+    assert_eq!(invalid_tickets, vec![1, 2, 3]);
+
+    assert_eq!(report.rule_matches[0], (String::from("class"), 4));
+    assert_eq!(report.never_satisfied(), vec!["zone"]);
+}
+
+#[test]
+fn test_validation_report_export() {
+    let report = example_report();
+
+    let mut csv = vec![];
+    report.write_csv(&mut csv).unwrap();
+    assert_eq!(String::from_utf8(csv).unwrap(), "\
+ticket,column,value,nearest_field,nearest_start,nearest_end,distance
+1,1,4,class,1,3,1
+2,0,55,seat,45,50,5
+3,2,12,row,6,11,1
+");
+
+    let mut json = vec![];
+    report.write_json(&mut json).unwrap();
+    let json = String::from_utf8(json).unwrap();
+    assert!(json.contains(r#"{"ticket": 0, "valid": true, "invalid_values": []},"#));
+    assert!(json.contains(r#"{"ticket": 3, "valid": false, "invalid_values": [{"column": 2, "value": 12, "nearest_range": {"field": "row", "start": 6, "end": 11, "distance": 1}}]}"#));
+    assert!(json.contains(r#""rule_matches": [{"rule": "class", "matches": 4}, {"rule": "row", "matches": 4}, {"rule": "seat", "matches": 5}, {"rule": "zone", "matches": 0}],"#));
+    assert!(json.contains(r#""never_satisfied": ["zone"]"#));
+}
+
+#[test]
+fn test_validation_report_edge_cases() {
+    // Two rules can share a name, and each keeps its own count
+    let ticket_format = TicketFormat {
+        fields: vec![
+            FieldConfig::from_input("zone: 1-1").unwrap(),
+            FieldConfig::from_input("zone: 2-2").unwrap(),
+        ]
+    };
+    let tickets = vec![PossibleTicket::from_input("1,2").unwrap(), PossibleTicket::from_input("2,3").unwrap()];
+    let mut json = vec![];
+    ValidationReport::new(&ticket_format, &tickets).write_json(&mut json).unwrap();
+    let json = String::from_utf8(json).unwrap();
+    assert!(json.contains(r#""rule_matches": [{"rule": "zone", "matches": 1}, {"rule": "zone", "matches": 2}],"#));
+
+    // Values at opposite ends of the i32 range are further apart than an i32 can hold
+    let ticket_format = TicketFormat { fields: vec![FieldConfig::from_input("zone: 2147483647-2147483647").unwrap()] };
+    let tickets = vec![PossibleTicket { field_values: vec![i32::MIN] }];
+    let report = ValidationReport::new(&ticket_format, &tickets);
+    assert_eq!(report.tickets[0].invalid_values[0].nearest_range,
+               Some(NearestRange { field: String::from("zone"), range: (i32::MAX, i32::MAX), distance: u32::MAX }));
+}
+
+#[test]
+fn test_parse_args() {
+    let args: Vec<String> = vec![];
+    let options = parse_args(&args).unwrap();
+    assert_eq!(options.filename, "input.txt");
+    assert_eq!(options.max_solutions, 2);
+
+    let args: Vec<String> = ["--solutions", "10", "notes.txt"].iter().map(|arg| arg.to_string()).collect();
+    let options = parse_args(&args).unwrap();
+    assert_eq!(options.filename, "notes.txt");
+    assert_eq!(options.max_solutions, 10);
+
+    for args in [vec!["--solutions"], vec!["--solutions", "0"], vec!["--solutions", "many"]].iter() {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        assert!(parse_args(&args).is_err());
+    }
+}
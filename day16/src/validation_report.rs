@@ -0,0 +1,169 @@
+use crate::{FieldRange, PossibleTicket, TicketFormat};
+use std::io;
+use std::io::Write;
+
+/// The range closest to an invalid value, and the field it belongs to.
+#[derive(Debug, PartialEq)]
+pub struct NearestRange {
+    pub field: String,
+    pub range: FieldRange,
+    pub distance: u32,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct InvalidValue {
+    pub column: usize,
+    pub value: i32,
+    /// Only missing when there are no rules at all.
+    pub nearest_range: Option<NearestRange>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct TicketReport {
+    /// Where the ticket is in the list of nearby tickets, starting from 0.
+    pub ticket: usize,
+    pub invalid_values: Vec<InvalidValue>,
+}
+
+impl TicketReport {
+    pub fn is_valid(&self) -> bool {
+        self.invalid_values.is_empty()
+    }
+}
+
+/// Which values on each nearby ticket no rule allows, and how many values each rule allows.
+#[derive(Debug)]
+pub struct ValidationReport {
+    pub tickets: Vec<TicketReport>,
+    /// For each rule, in the order of the ticket format, how many values on the nearby tickets
+    /// it allows.
+    pub rule_matches: Vec<(String, usize)>,
+}
+
+impl ValidationReport {
+    pub fn new(format: &TicketFormat, tickets: &[PossibleTicket]) -> ValidationReport {
+        let mut rule_matches: Vec<(String, usize)> = format.fields.iter()
+            .map(|field| (field.name.clone(), 0))
+            .collect();
+
+        let tickets = tickets.iter().enumerate().map(|(ticket, possible_ticket)| {
+            let mut invalid_values = vec![];
+
+            for (column, value) in possible_ticket.field_values.iter().enumerate() {
+                let mut valid_for_any_field = false;
+                for (field, field_config) in format.fields.iter().enumerate() {
+                    if field_config.is_valid_value(*value) {
+                        valid_for_any_field = true;
+                        rule_matches[field].1 += 1;
+                    }
+                }
+
+                if !valid_for_any_field {
+                    invalid_values.push(InvalidValue {
+                        column,
+                        value: *value,
+                        nearest_range: nearest_range(format, *value),
+                    });
+                }
+            }
+
+            TicketReport { ticket, invalid_values }
+        }).collect();
+
+        ValidationReport { tickets, rule_matches }
+    }
+
+    pub fn invalid_tickets(&self) -> impl Iterator<Item=&TicketReport> {
+        self.tickets.iter().filter(|ticket| !ticket.is_valid())
+    }
+
+    /// The rules none of the values on the nearby tickets satisfy.
+    pub fn never_satisfied(&self) -> Vec<&str> {
+        self.rule_matches.iter()
+            .filter(|(_, matches)| *matches == 0)
+            .map(|(name, _)| &name[..])
+            .collect()
+    }
+
+    /// One row per invalid value. Valid tickets don't get any rows.
+    pub fn write_csv<W: Write>(&self, mut out: W) -> io::Result<()> {
+        writeln!(out, "ticket,column,value,nearest_field,nearest_start,nearest_end,distance")?;
+        for ticket in self.invalid_tickets() {
+            for invalid_value in ticket.invalid_values.iter() {
+                write!(out, "{},{},{},", ticket.ticket, invalid_value.column, invalid_value.value)?;
+                match &invalid_value.nearest_range {
+                    Some(nearest) => writeln!(out, "{},{},{},{}", csv_escape(&nearest.field),
+                                              nearest.range.0, nearest.range.1, nearest.distance)?,
+                    None => writeln!(out, ",,,")?,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Every ticket, valid or not, along with the summary of how many values each rule allows.
+    pub fn write_json<W: Write>(&self, mut out: W) -> io::Result<()> {
+        writeln!(out, "{{")?;
+        writeln!(out, "  \"tickets\": [")?;
+        for (i, ticket) in self.tickets.iter().enumerate() {
+            let invalid_values: Vec<String> = ticket.invalid_values.iter().map(|invalid_value| {
+                let nearest_range = match &invalid_value.nearest_range {
+                    Some(nearest) => format!(
+                        "{{\"field\": {}, \"start\": {}, \"end\": {}, \"distance\": {}}}",
+                        json_string(&nearest.field), nearest.range.0, nearest.range.1, nearest.distance
+                    ),
+                    None => String::from("null"),
+                };
+                format!("{{\"column\": {}, \"value\": {}, \"nearest_range\": {}}}",
+                        invalid_value.column, invalid_value.value, nearest_range)
+            }).collect();
+
+            let separator = if i + 1 < self.tickets.len() { "," } else { "" };
+            writeln!(out, "    {{\"ticket\": {}, \"valid\": {}, \"invalid_values\": [{}]}}{}",
+                     ticket.ticket, ticket.is_valid(), invalid_values.join(", "), separator)?;
+        }
+        writeln!(out, "  ],")?;
+
+        let rule_matches: Vec<String> = self.rule_matches.iter()
+            .map(|(name, matches)| format!("{{\"rule\": {}, \"matches\": {}}}", json_string(name), matches))
+            .collect();
+        writeln!(out, "  \"rule_matches\": [{}],", rule_matches.join(", "))?;
+
+        let never_satisfied: Vec<String> = self.never_satisfied().iter().map(|name| json_string(name)).collect();
+        writeln!(out, "  \"never_satisfied\": [{}]", never_satisfied.join(", "))?;
+        writeln!(out, "}}")
+    }
+}
+
+fn nearest_range(format: &TicketFormat, value: i32) -> Option<NearestRange> {
+    format.fields.iter()
+        .flat_map(|field| field.valid_ranges.iter().map(move |range| (field, *range)))
+        .map(|(field, (start, end))| {
+            let distance = if value < start { start.abs_diff(value) } else { value.abs_diff(end) };
+            NearestRange { field: field.name.clone(), range: (start, end), distance }
+        })
+        .min_by_key(|nearest| nearest.distance)
+}
+
+fn csv_escape(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        String::from(value)
+    }
+}
+
+fn json_string(value: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
@@ -0,0 +1,118 @@
+use crate::CubeStatus;
+use core::fmt;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, PartialEq)]
+pub enum RuleError {
+    /// Rules look like `B3/S23`, or `B3,10/S2-4` when counts go past 9.
+    InvalidNotation(String),
+    InvalidCount(String),
+    /// Cubes with no active neighbours coming alive would fill the whole infinite grid.
+    BirthWithoutNeighbours,
+}
+
+impl Display for RuleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleError::InvalidNotation(rule) => write!(f, "Expected a rule like B3/S23, found `{}`", rule),
+            RuleError::InvalidCount(count) => write!(f, "`{}` is not a valid neighbour count", count),
+            RuleError::BirthWithoutNeighbours => write!(f, "Rules with B0 would activate the whole grid"),
+        }
+    }
+}
+
+/// A life-like rule: how many active neighbours bring an inactive cube to life (birth), and how
+/// many keep an active one alive (survival).
+#[derive(Debug, PartialEq, Clone)]
+pub struct LifeRule {
+    pub birth: Vec<usize>,
+    pub survival: Vec<usize>,
+}
+
+impl LifeRule {
+    /// The puzzle's rule, which is also Conway's: B3/S23.
+    pub fn conway() -> LifeRule {
+        LifeRule {
+            birth: vec![3],
+            survival: vec![2, 3],
+        }
+    }
+
+    /// Parses B/S notation. Each side is either a string of single digit counts (`S23`), or a
+    /// comma separated list of counts and ranges (`S2,3,10-12`) for the bigger counts of higher
+    /// dimensions. The letters can be either case.
+    pub fn parse(rule: &str) -> Result<LifeRule, RuleError> {
+        let invalid_notation = || RuleError::InvalidNotation(String::from(rule));
+        let (birth, survival) = rule.split_once('/').ok_or_else(invalid_notation)?;
+
+        let birth = birth.strip_prefix(['B', 'b']).ok_or_else(invalid_notation)?;
+        let survival = survival.strip_prefix(['S', 's']).ok_or_else(invalid_notation)?;
+
+        let birth = LifeRule::parse_counts(birth)?;
+        if birth.contains(&0) {
+            return Err(RuleError::BirthWithoutNeighbours);
+        }
+
+        Ok(LifeRule {
+            birth,
+            survival: LifeRule::parse_counts(survival)?,
+        })
+    }
+
+    fn parse_counts(counts: &str) -> Result<Vec<usize>, RuleError> {
+        let invalid_count = |count: &str| RuleError::InvalidCount(String::from(count));
+        let mut parsed = vec![];
+
+        if counts.contains([',', '-']) {
+            for count in counts.split(',') {
+                match count.split_once('-') {
+                    Some((start, end)) => {
+                        let start: usize = start.parse().map_err(|_| invalid_count(count))?;
+                        let end: usize = end.parse().map_err(|_| invalid_count(count))?;
+                        if start > end {
+                            return Err(invalid_count(count));
+                        }
+                        parsed.extend(start..=end);
+                    }
+                    None => parsed.push(count.parse().map_err(|_| invalid_count(count))?),
+                }
+            }
+        } else {
+            for digit in counts.chars() {
+                let count = digit.to_digit(10).ok_or_else(|| invalid_count(&digit.to_string()))?;
+                parsed.push(count as usize);
+            }
+        }
+
+        parsed.sort_unstable();
+        parsed.dedup();
+        Ok(parsed)
+    }
+
+    pub fn next_state(&self, cube_status: CubeStatus, active_neighbours: usize) -> CubeStatus {
+        let counts = match cube_status {
+            CubeStatus::Active => &self.survival,
+            CubeStatus::Inactive => &self.birth,
+        };
+
+        if counts.binary_search(&active_neighbours).is_ok() {
+            CubeStatus::Active
+        } else {
+            CubeStatus::Inactive
+        }
+    }
+}
+
+fn format_counts(counts: &[usize]) -> String {
+    if counts.iter().all(|count| *count < 10) {
+        counts.iter().map(|count| count.to_string()).collect()
+    } else {
+        counts.iter().map(|count| count.to_string()).collect::<Vec<String>>().join(",")
+    }
+}
+
+impl Display for LifeRule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "B{}/S{}", format_counts(&self.birth), format_counts(&self.survival))
+    }
+}
@@ -1,69 +1,151 @@
+mod life_rule;
+mod slice_sheet;
+#[cfg(test)]
 mod tests;
 
-use std::collections::HashMap;
-
-use itertools::iproduct;
-use std::i32::{MIN, MAX};
+use crate::life_rule::LifeRule;
+use crate::slice_sheet::write_slice_sheet;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
 use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process;
+
+const USAGE: &str = "Usage: day17 [--dimensions N] [--cycles N] [--rule B3/S23] [--no-symmetry] [--print] \
+[--png FILE] [--scale PIXELS] [seed file]";
+
+/// The most dimensions `main` can run, since each one needs its own copy of the engine.
+const MAX_DIMENSIONS: usize = 7;
 
-#[derive(Debug, Eq, Hash, Copy, Clone)]
-struct Point(i32, i32, i32, i32);
+/// A point in `D` dimensions. The first two are x and y, which the seed is laid out in.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+struct Point<const D: usize>([i32; D]);
 
-impl PartialEq for Point {
-    fn eq(&self, other: &Self) -> bool {
-        self.0 == other.0 && self.1 == other.1 && self.2 == other.2 && self.3 == other.3
+impl<const D: usize> Point<D> {
+    pub fn on_plane(x: i32, y: i32) -> Point<D> {
+        let mut coordinates = [0; D];
+        coordinates[0] = x;
+        coordinates[1] = y;
+        Point(coordinates)
     }
-}
 
-impl Point {
-    pub fn neighbours(&self) -> Vec<Point> {
-        let x_range = self.0 - 1..self.0 + 2;
-        let y_range = self.1 - 1..self.1 + 2;
-        let z_range = self.2 - 1..self.2 + 2;
-        let w_range = self.3 - 1..self.3 + 2;
-
-        let mut neighbours = vec![];
-        for (x, y, z, w) in iproduct!(x_range, y_range, z_range, w_range) {
-            if x == self.0 && y == self.1 && z == self.2  && w == self.3 {
-                continue;
-            }
+    #[cfg(test)]
+    pub fn neighbours(&self) -> Vec<Point<D>> {
+        neighbour_offsets::<D>().iter().map(|offset| self.offset_by(offset)).collect()
+    }
 
-            neighbours.push(
-                Point(x, y, z, w)
-            );
+    fn offset_by(&self, offset: &[i32; D]) -> Point<D> {
+        let mut coordinates = self.0;
+        for (coordinate, delta) in coordinates.iter_mut().zip(offset.iter()) {
+            *coordinate += delta;
         }
+        Point(coordinates)
+    }
+
+    /// The mirror image of this point with every coordinate past x and y made non-negative.
+    pub fn canonical(&self) -> Point<D> {
+        let mut coordinates = self.0;
+        for coordinate in coordinates.iter_mut().skip(2) {
+            *coordinate = coordinate.abs();
+        }
+        Point(coordinates)
+    }
+
+    pub fn is_canonical(&self) -> bool {
+        self.0.iter().skip(2).all(|coordinate| *coordinate >= 0)
+    }
 
-        neighbours
+    /// How many points this one stands for when only canonical points are stored: each
+    /// coordinate past x and y that isn't 0 has a mirror image.
+    pub fn multiplicity(&self) -> usize {
+        1 << self.0.iter().skip(2).filter(|coordinate| **coordinate != 0).count()
     }
 }
 
+/// Every combination of -1, 0 and 1 in each dimension, except not moving at all.
+fn neighbour_offsets<const D: usize>() -> Vec<[i32; D]> {
+    let combinations = 3usize.pow(D as u32);
+    (0..combinations)
+        .filter(|combination| *combination != combinations / 2)
+        .map(|combination| {
+            let mut offset = [0; D];
+            let mut digits = combination;
+            for delta in offset.iter_mut() {
+                *delta = (digits % 3) as i32 - 1;
+                digits /= 3;
+            }
+            offset
+        })
+        .collect()
+}
+
 #[derive(Debug, PartialEq, Copy, Clone)]
 enum CubeStatus {
     Active,
     Inactive,
 }
 
+/// An infinite grid of cubes in `D` dimensions. Only the active cubes are stored, and each tick
+/// only looks at them and their neighbours.
+///
+/// A seed laid out on the x/y plane stays mirror symmetric in every other dimension, so with
+/// `use_mirror_symmetry` only the cubes with non-negative coordinates past x and y are stored.
 #[derive(Debug)]
-struct Grid {
-    cubes: HashMap<Point, CubeStatus>
+struct Grid<const D: usize> {
+    active_cubes: HashSet<Point<D>>,
+    neighbour_offsets: Vec<[i32; D]>,
+    mirror_symmetric: bool,
+    rule: LifeRule,
 }
 
-impl Grid {
-    pub fn new() -> Grid {
+impl<const D: usize> Grid<D> {
+    pub fn new() -> Grid<D> {
+        assert!(D >= 2, "The seed needs at least two dimensions");
+
         Grid {
-            cubes: HashMap::new()
+            active_cubes: HashSet::new(),
+            neighbour_offsets: neighbour_offsets::<D>(),
+            mirror_symmetric: false,
+            rule: LifeRule::conway(),
+        }
+    }
+
+    pub fn set_rule(&mut self, rule: LifeRule) {
+        self.rule = rule;
+    }
+
+    /// Switches to only storing canonical cubes. Returns false, leaving the grid as it is, if the
+    /// active cubes aren't mirror symmetric in every dimension past x and y.
+    pub fn use_mirror_symmetry(&mut self) -> bool {
+        if self.mirror_symmetric {
+            return true;
         }
+
+        let stored_cubes: usize = self.active_cubes.iter()
+            .filter(|p| p.is_canonical())
+            .map(|p| p.multiplicity())
+            .sum();
+        let symmetric = stored_cubes == self.active_cubes.len()
+            && self.active_cubes.iter().all(|p| self.active_cubes.contains(&p.canonical()));
+        if !symmetric {
+            return false;
+        }
+
+        self.active_cubes.retain(|p| p.is_canonical());
+        self.mirror_symmetric = true;
+        true
     }
 
-    pub fn new_from_input(input: &Vec<String>) -> Grid {
+    /// Lays the seed out on the x/y plane, with every other coordinate at 0.
+    pub fn new_from_input(input: &[String]) -> Grid<D> {
         let mut grid = Grid::new();
 
-        const Z: i32 = 0;
-        const W: i32 = 0;
         for (y, row) in input.iter().enumerate() {
             for (x, col) in row.chars().enumerate() {
                 if col == '#' {
-                    grid.update_cube_at_point(&Point(x as i32, y as i32, Z, W), CubeStatus::Active);
+                    grid.update_cube_at_point(&Point::on_plane(x as i32, y as i32), CubeStatus::Active);
                 }
             }
         }
@@ -71,177 +153,321 @@ impl Grid {
         grid
     }
 
-    pub fn point_to_cube_status(&self, p: &Point) -> CubeStatus {
-        match self.cubes.get(p) {
-            None => CubeStatus::Inactive,
-            Some(cube_status) => cube_status.clone()
+    pub fn point_to_cube_status(&self, p: &Point<D>) -> CubeStatus {
+        let active = if self.mirror_symmetric {
+            self.active_cubes.contains(&p.canonical())
+        } else {
+            self.active_cubes.contains(p)
+        };
+
+        if active {
+            CubeStatus::Active
+        } else {
+            CubeStatus::Inactive
         }
     }
 
-    pub fn update_cube_at_point(&mut self, p: &Point, cube_status: CubeStatus) {
-        let p = p.clone();
-        self.cubes.insert(p, cube_status);
+    /// With mirror symmetry this updates every mirror image of the point along with it.
+    pub fn update_cube_at_point(&mut self, p: &Point<D>, cube_status: CubeStatus) {
+        let p = if self.mirror_symmetric { p.canonical() } else { *p };
+        match cube_status {
+            CubeStatus::Active => self.active_cubes.insert(p),
+            CubeStatus::Inactive => self.active_cubes.remove(&p),
+        };
     }
 
-    pub fn next_state_for_point(&self, p: &Point) -> CubeStatus {
-        let neighbours = p.neighbours();
-        let mut active_neighbours = 0;
-        let mut inactive_neighbours = 0;
+    #[cfg(test)]
+    pub fn next_state_for_point(&self, p: &Point<D>) -> CubeStatus {
+        let active_neighbours = p.neighbours().iter()
+            .filter(|neighbour| self.point_to_cube_status(neighbour) == CubeStatus::Active)
+            .count();
 
-        for neighbour in neighbours {
-            let state = self.point_to_cube_status(&neighbour);
-            match state {
-                CubeStatus::Active => {
-                    active_neighbours += 1;
-                }
-                CubeStatus::Inactive => {
-                    inactive_neighbours += 1;
-                }
-            }
+        self.rule.next_state(self.point_to_cube_status(p), active_neighbours)
+    }
+
+    /// The smallest and largest coordinate in each dimension that could have an active cube
+    /// after the next tick, or `None` when no cubes are active and none ever will be.
+    pub fn get_extents_of_grid_to_consider(&self) -> Option<[(i32, i32); D]> {
+        if self.active_cubes.is_empty() {
+            return None;
         }
 
-        match self.point_to_cube_status(p) {
-            CubeStatus::Active => {
-                if active_neighbours == 2 || active_neighbours == 3 {
-                    CubeStatus::Active
-                } else {
-                    CubeStatus::Inactive
-                }
+        let mut extents = [(i32::MAX, i32::MIN); D];
+
+        for p in self.active_cubes.iter() {
+            for (extent, coordinate) in extents.iter_mut().zip(p.0.iter()) {
+                extent.0 = extent.0.min(*coordinate);
+                extent.1 = extent.1.max(*coordinate);
             }
-            CubeStatus::Inactive => {
-                if active_neighbours == 3 {
-                    CubeStatus::Active
-                } else {
-                    CubeStatus::Inactive
-                }
+        }
+
+        if self.mirror_symmetric {
+            for extent in extents.iter_mut().skip(2) {
+                extent.0 = -extent.1;
             }
         }
-    }
 
-    pub fn get_extents_of_grid_to_consider(&self) -> ((i32, i32), (i32, i32), (i32, i32), (i32, i32)) {
-        let (mut min_x, mut min_y, mut min_z, mut min_w) = (MAX, MAX, MAX, MAX);
-        let (mut max_x, mut max_y, mut max_z, mut max_w) = (MIN, MIN, MIN, MIN);
+        for extent in extents.iter_mut() {
+            *extent = (extent.0.saturating_sub(1), extent.1.saturating_add(1));
+        }
+        Some(extents)
+    }
 
-        for (p, state) in self.cubes.iter() {
-            if *state == CubeStatus::Active {
-                if p.0 < min_x {
-                    min_x = p.0;
-                }
-                if p.1 < min_y {
-                    min_y = p.1;
-                }
-                if p.2 < min_z {
-                    min_z = p.2;
-                }
-                if p.3 < min_w {
-                    min_w = p.3;
+    pub fn tick(&mut self) {
+        // Every active cube adds one to the count of each of its neighbours, so only cubes next
+        // to an active one get a count at all. Active cubes start at 0, so a rule like S0 still
+        // gets to keep the ones without any active neighbours.
+        let mut active_neighbours: HashMap<Point<D>, usize> = self.active_cubes.iter()
+            .map(|p| (*p, 0))
+            .collect();
+        for p in self.active_cubes.iter() {
+            for offset in self.neighbour_offsets.iter() {
+                let neighbour = p.offset_by(offset);
+                if !self.mirror_symmetric {
+                    *active_neighbours.entry(neighbour).or_insert(0) += 1;
+                    continue;
                 }
 
-                if p.0 > max_x {
-                    max_x = p.0;
-                }
-                if p.1 > max_y {
-                    max_y = p.1;
-                }
-                if p.2 > max_z {
-                    max_z = p.2;
-                }
-                if p.3 > max_w {
-                    max_w = p.3;
+                // Only canonical cubes are counted. A canonical cube next to p is also next to
+                // the mirror image of p across every dimension where p is at 1 and the cube is
+                // at 0, and all of those images are active too.
+                if !neighbour.is_canonical() {
+                    continue;
                 }
+                let mirrored_dimensions = p.0.iter().zip(neighbour.0.iter())
+                    .skip(2)
+                    .filter(|(from, to)| **from == 1 && **to == 0)
+                    .count();
+                *active_neighbours.entry(neighbour).or_insert(0) += 1 << mirrored_dimensions;
             }
         }
 
-        ((min_x - 1, max_x + 1),
-         (min_y - 1, max_y + 1),
-         (min_z - 1, max_z + 1),
-         (min_w - 1, max_w + 1))
+        self.active_cubes = active_neighbours.into_iter()
+            .filter(|(p, count)| {
+                self.rule.next_state(self.point_to_cube_status(p), *count) == CubeStatus::Active
+            })
+            .map(|(p, _)| p)
+            .collect();
     }
 
-    pub fn tick(&mut self) {
-        let (
-            (min_x, max_x),
-            (min_y, max_y),
-            (min_z, max_z),
-            (min_w, max_w),
-        ) = self.get_extents_of_grid_to_consider();
-
-        let mut new_cubes = HashMap::new();
-        for (x, y, z, w) in iproduct!(min_x .. max_x + 1, min_y .. max_y + 1, min_z .. max_y + 1, min_w .. max_w + 1) {
-            let p = Point(x, y, z, w);
-            new_cubes.insert(p, self.next_state_for_point(&p));
+    pub fn number_of_active_cubes(&self) -> usize {
+        if self.mirror_symmetric {
+            self.active_cubes.iter().map(|p| p.multiplicity()).sum()
+        } else {
+            self.active_cubes.len()
         }
+    }
 
-        self.cubes = new_cubes;
+    /// How many cubes are actually stored, which is fewer than are active with mirror symmetry.
+    pub fn number_of_stored_cubes(&self) -> usize {
+        self.active_cubes.len()
     }
 
-    pub fn number_of_active_cubes(&self) -> usize {
-        let mut n_active = 0usize;
+    /// Prints an x/y slice for every combination of the other coordinates in `ranges`.
+    pub fn print(&self, ranges: &[Range<i32>; D]) {
+        let mut slice = [0; D];
+        self.print_slices(ranges, 2, &mut slice);
+    }
 
-        for (_, state) in self.cubes.iter() {
-            if *state == CubeStatus::Active {
-                n_active += 1;
+    fn print_slices(&self, ranges: &[Range<i32>; D], dimension: usize, slice: &mut [i32; D]) {
+        if dimension < D {
+            for coordinate in ranges[dimension].clone() {
+                slice[dimension] = coordinate;
+                self.print_slices(ranges, dimension + 1, slice);
             }
+            return;
+        }
+
+        let labels: Vec<String> = (2..D)
+            .map(|dimension| format!("{} = {}", dimension_name(dimension), slice[dimension]))
+            .collect();
+        if !labels.is_empty() {
+            println!("{}", labels.join("; "));
         }
 
-        n_active
+        for y in ranges[1].clone() {
+            for x in ranges[0].clone() {
+                let mut p = *slice;
+                p[0] = x;
+                p[1] = y;
+                print!("{}", match self.point_to_cube_status(&Point(p)) {
+                    CubeStatus::Active => "#",
+                    CubeStatus::Inactive => "."
+                });
+            }
+
+            println!();
+        }
+        println!()
     }
 
-    pub fn print(&self, x_range: Range<i32>, y_range: Range<i32>, z_range: Range<i32>, w_range: Range<i32>) {
-        let x_range: Vec<i32> = x_range.collect();
-        let y_range: Vec<i32> = y_range.collect();
-        let z_range: Vec<i32> = z_range.collect();
-        let w_range: Vec<i32> = w_range.collect();
+    /// Prints nothing once every cube is inactive.
+    pub fn print_extents(&self) {
+        let extents = match self.get_extents_of_grid_to_consider() {
+            Some(extents) => extents,
+            None => return,
+        };
+        let mut ranges: [Range<i32>; D] = [(); D].map(|_| 0..0);
+        for (range, (min, max)) in ranges.iter_mut().zip(extents.iter()) {
+            *range = *min..*max + 1;
+        }
 
-        for w in &w_range {
-            for z in &z_range {
-                println!("z = {}; w = {}", z, w);
-                for y in &y_range {
-                    for x in &x_range {
-                        print!("{}", match self.point_to_cube_status(&Point(*x, *y, *z, *w)) {
-                            CubeStatus::Active => "#",
-                            CubeStatus::Inactive => "."
-                        });
-                    }
+        self.print(&ranges);
+    }
+}
 
-                    println!();
-                }
-                println!()
+/// x, y, z and w, and then d4, d5 and so on.
+fn dimension_name(dimension: usize) -> String {
+    match dimension {
+        0 => String::from("x"),
+        1 => String::from("y"),
+        2 => String::from("z"),
+        3 => String::from("w"),
+        _ => format!("d{}", dimension),
+    }
+}
+
+/// Reads a seed of `#` (active) and `.` (inactive) cubes, reporting anything else with its line
+/// and column.
+fn get_input(filename: &str) -> Result<Vec<String>, String> {
+    let file = File::open(Path::new(filename)).map_err(|error| format!("Can't read {}: {}", filename, error))?;
+
+    let mut input = vec![];
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|error| format!("Can't read {}: {}", filename, error))?;
+        if let Some((column, c)) = line.chars().enumerate().find(|(_, c)| *c != '#' && *c != '.') {
+            return Err(format!("Line {}, column {}: Seeds can only contain # and ., not '{}'", i + 1, column + 1, c));
+        }
+        input.push(line);
+    }
+
+    Ok(input)
+}
+
+/// Turns `sheet.png` into `sheet.4d.png`, so runs in different dimensions don't overwrite each other.
+fn path_for_dimensions(path: &str, dimensions: usize) -> PathBuf {
+    let path = Path::new(path);
+    let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or("");
+    path.with_extension(format!("{}d.{}", dimensions, extension))
+}
+
+/// Returns the number of active cubes, and how many of them had to be stored.
+fn run<const D: usize>(input: &[String], options: &Options) -> (usize, usize) {
+    let mut grid = Grid::<D>::new_from_input(input);
+    grid.set_rule(options.rule.clone());
+    if options.symmetry {
+        // Seeds are always on the x/y plane, so they're always symmetric
+        grid.use_mirror_symmetry();
+    }
+
+    for _ in 1..=options.cycles {
+        grid.tick();
+    }
+
+    if options.print {
+        grid.print_extents();
+    }
+    if let Some(path) = &options.png {
+        let path = if options.dimensions.len() > 1 { path_for_dimensions(path, D) } else { PathBuf::from(path) };
+        if let Err(error) = write_slice_sheet(&grid, &path, options.scale) {
+            eprintln!("Writing {} failed: {}", path.display(), error);
+            process::exit(1);
+        }
+    }
+    (grid.number_of_active_cubes(), grid.number_of_stored_cubes())
+}
+
+fn run_in_dimensions(dimensions: usize, input: &[String], options: &Options) -> (usize, usize) {
+    match dimensions {
+        2 => run::<2>(input, options),
+        3 => run::<3>(input, options),
+        4 => run::<4>(input, options),
+        5 => run::<5>(input, options),
+        6 => run::<6>(input, options),
+        7 => run::<7>(input, options),
+        _ => unreachable!("Dimensions are checked when parsing the arguments"),
+    }
+}
+
+struct Options {
+    filename: String,
+    dimensions: Vec<usize>,
+    cycles: usize,
+    rule: LifeRule,
+    symmetry: bool,
+    print: bool,
+    png: Option<String>,
+    scale: u32,
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut dimensions = vec![];
+    let mut cycles = 6;
+    let mut filename = String::from("input.txt");
+    let mut rule = LifeRule::conway();
+    let mut symmetry = true;
+    let mut print = false;
+    let mut png = None;
+    let mut scale = 8;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match &arg[..] {
+            "--dimensions" => {
+                let value = args.next()
+                    .and_then(|value| value.parse().ok())
+                    .filter(|value| (2..=MAX_DIMENSIONS).contains(value))
+                    .ok_or_else(|| format!("--dimensions expects a number from 2 to {}", MAX_DIMENSIONS))?;
+                dimensions.push(value);
+            }
+            "--cycles" => {
+                cycles = args.next()
+                    .and_then(|value| value.parse().ok())
+                    .ok_or("--cycles expects a number")?;
             }
+            "--rule" => {
+                let value = args.next().ok_or("--rule expects a rule like B3/S23")?;
+                rule = LifeRule::parse(value).map_err(|error| error.to_string())?;
+            }
+            "--no-symmetry" => symmetry = false,
+            "--print" => print = true,
+            "--png" => png = Some(args.next().cloned().ok_or("--png expects a path")?),
+            "--scale" => {
+                scale = args.next()
+                    .and_then(|value| value.parse().ok())
+                    .filter(|scale| *scale > 0)
+                    .ok_or("--scale expects a positive number of pixels")?;
+            }
+            _ => filename = arg.clone(),
         }
     }
 
-    pub fn print_extents(&self) {
-        let (
-            (min_x, max_x),
-            (min_y, max_y),
-            (min_z, max_z),
-            (min_w, max_w),
-        ) = self.get_extents_of_grid_to_consider();
-
-        self.print(
-            min_x..max_x + 1,
-            min_y..max_y + 1,
-            min_z..max_z + 1,
-            min_w..max_w + 1,
-        );
+    if dimensions.is_empty() {
+        dimensions = vec![3, 4];
     }
+    Ok(Options { filename, dimensions, cycles, rule, symmetry, print, png, scale })
 }
 
 fn main() {
-    let input = vec![
-        String::from("######.#"),
-        String::from("#.###.#."),
-        String::from("###....."),
-        String::from("#.####.."),
-        String::from("##.#.###"),
-        String::from(".######."),
-        String::from("###.####"),
-        String::from("######.#"),
-    ];
-    let mut grid = Grid::new_from_input(&input);
-    for _ in 1..=6 {
-        grid.tick();
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}\n{}", error, USAGE);
+            process::exit(1);
+        }
+    };
+
+    let input = match get_input(&options.filename) {
+        Ok(input) => input,
+        Err(error) => {
+            eprintln!("{}", error);
+            process::exit(1);
+        }
+    };
+
+    for dimensions in options.dimensions.iter() {
+        let (active_cubes, stored_cubes) = run_in_dimensions(*dimensions, &input, &options);
+        println!("Active cubes in {} dimensions: {} (stored {})", dimensions, active_cubes, stored_cubes);
     }
-    println!("Active neighbours: {}", grid.number_of_active_cubes());
 }
@@ -0,0 +1,74 @@
+use crate::{CubeStatus, Grid, Point};
+use image::{ImageResult, Rgb, RgbImage};
+use std::path::Path;
+
+const ACTIVE_COLOR: Rgb<u8> = Rgb([240, 200, 60]);
+const INACTIVE_COLOR: Rgb<u8> = Rgb([30, 30, 50]);
+const ORIGIN_COLOR: Rgb<u8> = Rgb([60, 60, 90]);
+const GUTTER_COLOR: Rgb<u8> = Rgb([255, 255, 255]);
+
+/// Space between slices, in cubes.
+const GUTTER: u32 = 1;
+
+/// Every x/y slice of the grid's extents, laid out as a sheet: one column per z, and one row for
+/// each combination of the coordinates after z. Each cube is a `scale` x `scale` square, and the
+/// slices through the origin of the other coordinates are drawn a little lighter. Without any
+/// active cubes there are no slices, and the sheet is just a single gutter square.
+pub fn render_slice_sheet<const D: usize>(grid: &Grid<D>, scale: u32) -> RgbImage {
+    let extents = match grid.get_extents_of_grid_to_consider() {
+        Some(extents) => extents,
+        None => return RgbImage::from_pixel(GUTTER * scale, GUTTER * scale, GUTTER_COLOR),
+    };
+    let slice_width = (extents[0].1 - extents[0].0 + 1) as u32;
+    let slice_height = (extents[1].1 - extents[1].0 + 1) as u32;
+
+    // The coordinates past x and y of every slice, in the order they're laid out
+    let mut slices = vec![[0; D]];
+    for (dimension, (min, max)) in extents.iter().enumerate().skip(2) {
+        slices = slices.iter()
+            .flat_map(|slice| (*min..=*max).map(move |coordinate| {
+                let mut slice = *slice;
+                slice[dimension] = coordinate;
+                slice
+            }))
+            .collect();
+    }
+
+    let columns = if D > 2 { (extents[2].1 - extents[2].0 + 1) as u32 } else { 1 };
+    let rows = slices.len() as u32 / columns;
+
+    let width = (columns * (slice_width + GUTTER) + GUTTER) * scale;
+    let height = (rows * (slice_height + GUTTER) + GUTTER) * scale;
+    let mut sheet = RgbImage::from_pixel(width, height, GUTTER_COLOR);
+
+    for (i, slice) in slices.iter().enumerate() {
+        let left = GUTTER + (i as u32 % columns) * (slice_width + GUTTER);
+        let top = GUTTER + (i as u32 / columns) * (slice_height + GUTTER);
+        let through_origin = slice.iter().skip(2).all(|coordinate| *coordinate == 0);
+
+        for y in 0..slice_height {
+            for x in 0..slice_width {
+                let mut p = *slice;
+                p[0] = extents[0].0 + x as i32;
+                p[1] = extents[1].0 + y as i32;
+
+                let color = match grid.point_to_cube_status(&Point(p)) {
+                    CubeStatus::Active => ACTIVE_COLOR,
+                    CubeStatus::Inactive if through_origin => ORIGIN_COLOR,
+                    CubeStatus::Inactive => INACTIVE_COLOR,
+                };
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        sheet.put_pixel((left + x) * scale + dx, (top + y) * scale + dy, color);
+                    }
+                }
+            }
+        }
+    }
+
+    sheet
+}
+
+pub fn write_slice_sheet<const D: usize>(grid: &Grid<D>, path: &Path, scale: u32) -> ImageResult<()> {
+    render_slice_sheet(grid, scale).save(path)
+}
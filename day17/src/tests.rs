@@ -1,39 +1,42 @@
-use crate::*;
-use itertools::assert_equal;
+use crate::{Point, Grid, CubeStatus, neighbour_offsets, parse_args, get_input, path_for_dimensions};
+use crate::life_rule::{LifeRule, RuleError};
+use crate::slice_sheet::render_slice_sheet;
+use std::fs;
+use std::path::PathBuf;
 
 #[test]
 fn test_point_neighbours() {
-    let p = Point(0, 0, 0, 0);
+    let p = Point([0, 0, 0, 0]);
     let neighbours = p.neighbours();
     assert_eq!(neighbours.len(), 80);
 }
 
 #[test]
 fn test_grid_point_to_cube_status() {
-    let mut grid = Grid::new();
-    assert_eq!(grid.point_to_cube_status(&Point(0, 0, 0, 0)), CubeStatus::Inactive);
+    let grid = Grid::new();
+    assert_eq!(grid.point_to_cube_status(&Point([0, 0, 0, 0])), CubeStatus::Inactive);
 }
 
 #[test]
 fn test_grid_update_cube_at_point() {
     let mut grid = Grid::new();
-    grid.update_cube_at_point(&Point(1, 1, 1, 0), CubeStatus::Active);
+    grid.update_cube_at_point(&Point([1, 1, 1, 0]), CubeStatus::Active);
 
-    assert_eq!(grid.point_to_cube_status(&Point(0, 0, 0, 0)), CubeStatus::Inactive);
-    assert_eq!(grid.point_to_cube_status(&Point(1, 1, 1, 0)), CubeStatus::Active);
+    assert_eq!(grid.point_to_cube_status(&Point([0, 0, 0, 0])), CubeStatus::Inactive);
+    assert_eq!(grid.point_to_cube_status(&Point([1, 1, 1, 0])), CubeStatus::Active);
 }
 
 #[test]
 fn test_grid_next_state_for_point() {
     let mut grid = Grid::new();
-    grid.update_cube_at_point(&Point(1, 0, 0, 0), CubeStatus::Active);
-    grid.update_cube_at_point(&Point(2, 1, 0, 0), CubeStatus::Active);
-    grid.update_cube_at_point(&Point(0, 2, 0, 0), CubeStatus::Active);
-    grid.update_cube_at_point(&Point(1, 2, 0, 0), CubeStatus::Active);
-    grid.update_cube_at_point(&Point(2, 2, 0, 0), CubeStatus::Active);
+    grid.update_cube_at_point(&Point([1, 0, 0, 0]), CubeStatus::Active);
+    grid.update_cube_at_point(&Point([2, 1, 0, 0]), CubeStatus::Active);
+    grid.update_cube_at_point(&Point([0, 2, 0, 0]), CubeStatus::Active);
+    grid.update_cube_at_point(&Point([1, 2, 0, 0]), CubeStatus::Active);
+    grid.update_cube_at_point(&Point([2, 2, 0, 0]), CubeStatus::Active);
 
     assert_eq!(
-        grid.next_state_for_point(&Point(0, 1, 0, 0)),
+        grid.next_state_for_point(&Point([0, 1, 0, 0])),
         CubeStatus::Active
     );
 }
@@ -41,18 +44,18 @@ fn test_grid_next_state_for_point() {
 #[test]
 fn test_grid_get_extents_of_grid_to_consider() {
     let mut grid = Grid::new();
-    grid.update_cube_at_point(&Point(1, 0, 0, 0), CubeStatus::Active);
-    grid.update_cube_at_point(&Point(2, 1, 0, 0), CubeStatus::Active);
-    grid.update_cube_at_point(&Point(0, 2, 0, 0), CubeStatus::Active);
-    grid.update_cube_at_point(&Point(1, 2, 0, 0), CubeStatus::Active);
-    grid.update_cube_at_point(&Point(2, 2, 0, 0), CubeStatus::Active);
+    grid.update_cube_at_point(&Point([1, 0, 0, 0]), CubeStatus::Active);
+    grid.update_cube_at_point(&Point([2, 1, 0, 0]), CubeStatus::Active);
+    grid.update_cube_at_point(&Point([0, 2, 0, 0]), CubeStatus::Active);
+    grid.update_cube_at_point(&Point([1, 2, 0, 0]), CubeStatus::Active);
+    grid.update_cube_at_point(&Point([2, 2, 0, 0]), CubeStatus::Active);
 
-    let (
+    let [
         (min_x, max_x),
         (min_y, max_y),
         (min_z, max_z),
         (min_w, max_w),
-    ) = grid.get_extents_of_grid_to_consider();
+    ] = grid.get_extents_of_grid_to_consider().unwrap();
 
     assert_eq!(min_x, -1);
     assert_eq!(max_x, 3);
@@ -70,11 +73,11 @@ fn test_grid_get_extents_of_grid_to_consider() {
 #[test]
 fn test_grid_number_of_active_cubes() {
     let mut grid = Grid::new();
-    grid.update_cube_at_point(&Point(1, 0, 0, 0), CubeStatus::Active);
-    grid.update_cube_at_point(&Point(2, 1, 0, 0), CubeStatus::Active);
-    grid.update_cube_at_point(&Point(0, 2, 0, 0), CubeStatus::Active);
-    grid.update_cube_at_point(&Point(1, 2, 0, 0), CubeStatus::Active);
-    grid.update_cube_at_point(&Point(2, 2, 0, 0), CubeStatus::Active);
+    grid.update_cube_at_point(&Point([1, 0, 0, 0]), CubeStatus::Active);
+    grid.update_cube_at_point(&Point([2, 1, 0, 0]), CubeStatus::Active);
+    grid.update_cube_at_point(&Point([0, 2, 0, 0]), CubeStatus::Active);
+    grid.update_cube_at_point(&Point([1, 2, 0, 0]), CubeStatus::Active);
+    grid.update_cube_at_point(&Point([2, 2, 0, 0]), CubeStatus::Active);
 
     assert_eq!(grid.number_of_active_cubes(), 5);
 }
@@ -82,11 +85,11 @@ fn test_grid_number_of_active_cubes() {
 #[test]
 fn test_grid_tick() {
     let mut grid = Grid::new();
-    grid.update_cube_at_point(&Point(1, 0, 0, 0), CubeStatus::Active);
-    grid.update_cube_at_point(&Point(2, 1, 0, 0), CubeStatus::Active);
-    grid.update_cube_at_point(&Point(0, 2, 0, 0), CubeStatus::Active);
-    grid.update_cube_at_point(&Point(1, 2, 0, 0), CubeStatus::Active);
-    grid.update_cube_at_point(&Point(2, 2, 0, 0), CubeStatus::Active);
+    grid.update_cube_at_point(&Point([1, 0, 0, 0]), CubeStatus::Active);
+    grid.update_cube_at_point(&Point([2, 1, 0, 0]), CubeStatus::Active);
+    grid.update_cube_at_point(&Point([0, 2, 0, 0]), CubeStatus::Active);
+    grid.update_cube_at_point(&Point([1, 2, 0, 0]), CubeStatus::Active);
+    grid.update_cube_at_point(&Point([2, 2, 0, 0]), CubeStatus::Active);
 
     for _ in 1..=6 {
         grid.tick();
@@ -102,11 +105,322 @@ fn test_grid_new_from_input() {
         String::from("..#"),
         String::from("###"),
     ];
-    let mut grid = Grid::new_from_input(&input);
+    let mut grid = Grid::<4>::new_from_input(&input);
 
     for _ in 1..=6 {
         grid.tick();
     }
 
     assert_eq!(grid.number_of_active_cubes(), 848);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_neighbours_in_any_dimension() {
+    assert_eq!(Point([0, 0]).neighbours().len(), 8);
+    assert_eq!(Point([0, 0, 0]).neighbours().len(), 26);
+    assert_eq!(Point([0, 0, 0, 0, 0]).neighbours().len(), 242);
+
+    let offsets = neighbour_offsets::<2>();
+    assert!(!offsets.contains(&[0, 0]));
+    assert!(offsets.contains(&[-1, 1]));
+    assert!(Point([3, 4]).neighbours().contains(&Point([2, 5])));
+}
+
+#[test]
+fn test_grid_in_other_dimensions() {
+    let input = vec![
+        String::from(".#."),
+        String::from("..#"),
+        String::from("###"),
+    ];
+
+    let mut grid = Grid::<3>::new_from_input(&input);
+    for _ in 1..=6 {
+        grid.tick();
+    }
+    assert_eq!(grid.number_of_active_cubes(), 112);
+
+    // A glider in 2D keeps its 5 cubes and moves one step diagonally every 4 ticks
+    let mut grid = Grid::<2>::new_from_input(&input);
+    for _ in 1..=4 {
+        grid.tick();
+    }
+    assert_eq!(grid.number_of_active_cubes(), 5);
+    assert_eq!(grid.point_to_cube_status(&Point([2, 3])), CubeStatus::Active);
+    assert_eq!(grid.get_extents_of_grid_to_consider(), Some([(0, 4), (0, 4)]));
+}
+
+#[test]
+fn test_grid_tick_removes_inactive_cubes() {
+    let mut grid = Grid::new();
+    grid.update_cube_at_point(&Point([0, 0, 0]), CubeStatus::Active);
+    grid.tick();
+
+    assert_eq!(grid.number_of_active_cubes(), 0);
+    assert_eq!(grid.point_to_cube_status(&Point([0, 0, 0])), CubeStatus::Inactive);
+    assert_eq!(grid.get_extents_of_grid_to_consider(), None);
+}
+
+#[test]
+fn test_grid_get_extents_of_empty_grid() {
+    assert_eq!(Grid::<3>::new_from_input(&[]).get_extents_of_grid_to_consider(), None);
+
+    let mut grid = Grid::<4>::new_from_input(&[String::from("#")]);
+    assert!(grid.use_mirror_symmetry());
+    grid.tick();
+    assert_eq!(grid.get_extents_of_grid_to_consider(), None);
+}
+
+#[test]
+fn test_parse_args() {
+    let options = parse_args(&[]).unwrap();
+    assert_eq!(options.dimensions, vec![3, 4]);
+    assert_eq!(options.cycles, 6);
+    assert!(options.symmetry);
+
+    let args: Vec<String> = ["--dimensions", "5", "--cycles", "2", "--no-symmetry"].iter().map(|arg| arg.to_string()).collect();
+    let options = parse_args(&args).unwrap();
+    assert_eq!(options.dimensions, vec![5]);
+    assert_eq!(options.cycles, 2);
+    assert!(!options.symmetry);
+
+    let args: Vec<String> = ["--rule", "B36/S23", "seed.txt", "--png", "sheet.png"].iter().map(|arg| arg.to_string()).collect();
+    let options = parse_args(&args).unwrap();
+    assert_eq!(options.rule, LifeRule::parse("B36/S23").unwrap());
+    assert_eq!(options.filename, "seed.txt");
+    assert_eq!(options.png, Some(String::from("sheet.png")));
+
+    assert!(parse_args(&[String::from("--rule"), String::from("B0/S")]).is_err());
+    assert!(parse_args(&[String::from("--scale"), String::from("0")]).is_err());
+    assert!(parse_args(&[String::from("--dimensions"), String::from("1")]).is_err());
+    assert!(parse_args(&[String::from("--dimensions"), String::from("8")]).is_err());
+}
+
+/// The cubes active after the next tick, worked out the slow way by checking every point in the
+/// extents, sorted so they can be compared.
+fn active_after_tick_by_scanning<const D: usize>(grid: &Grid<D>) -> Vec<[i32; D]> {
+    let mut points = vec![[0; D]];
+    for (dimension, (min, max)) in grid.get_extents_of_grid_to_consider().unwrap().iter().enumerate() {
+        points = points.iter()
+            .flat_map(|point| (*min..=*max).map(move |coordinate| {
+                let mut point = *point;
+                point[dimension] = coordinate;
+                point
+            }))
+            .collect();
+    }
+
+    let mut active: Vec<[i32; D]> = points.into_iter()
+        .filter(|p| grid.next_state_for_point(&Point(*p)) == CubeStatus::Active)
+        .collect();
+    active.sort_unstable();
+    active
+}
+
+fn active_cubes<const D: usize>(grid: &Grid<D>) -> Vec<[i32; D]> {
+    let mut active: Vec<[i32; D]> = grid.active_cubes.iter().map(|p| p.0).collect();
+    active.sort_unstable();
+    active
+}
+
+#[test]
+fn test_grid_tick_on_non_square_seed() {
+    // Taller than it is wide, and with cubes off the x/y plane, so the bounds in every dimension
+    // differ. Scanning z up to max_y instead of max_z would miss cubes here.
+    let mut grid = Grid::<4>::new_from_input(&[
+        String::from(".#"),
+        String::from("#."),
+        String::from("##"),
+        String::from(".#"),
+        String::from("#."),
+    ]);
+    grid.update_cube_at_point(&Point([0, 0, 3, -1]), CubeStatus::Active);
+    grid.update_cube_at_point(&Point([1, 0, 3, -1]), CubeStatus::Active);
+    grid.update_cube_at_point(&Point([0, 1, 4, -2]), CubeStatus::Active);
+
+    for _ in 1..=3 {
+        let expected = active_after_tick_by_scanning(&grid);
+        grid.tick();
+        assert_eq!(active_cubes(&grid), expected);
+    }
+}
+
+#[test]
+fn test_point_symmetry() {
+    let p = Point([-1, 2, -3, 0, 4]);
+    assert_eq!(p.canonical(), Point([-1, 2, 3, 0, 4]));
+    assert!(!p.is_canonical());
+    assert!(p.canonical().is_canonical());
+    assert_eq!(p.multiplicity(), 4);
+    assert_eq!(Point([5, 5, 0, 0]).multiplicity(), 1);
+}
+
+#[test]
+fn test_grid_with_mirror_symmetry() {
+    let input = vec![
+        String::from(".#."),
+        String::from("..#"),
+        String::from("###"),
+    ];
+
+    let mut grid = Grid::<4>::new_from_input(&input);
+    assert!(grid.use_mirror_symmetry());
+    let mut full_grid = Grid::<4>::new_from_input(&input);
+
+    for _ in 1..=6 {
+        grid.tick();
+        full_grid.tick();
+
+        assert_eq!(grid.number_of_active_cubes(), full_grid.number_of_active_cubes());
+        assert_eq!(grid.get_extents_of_grid_to_consider(), full_grid.get_extents_of_grid_to_consider());
+        for p in full_grid.active_cubes.iter() {
+            assert_eq!(grid.point_to_cube_status(p), CubeStatus::Active);
+        }
+    }
+    assert_eq!(grid.number_of_active_cubes(), 848);
+    assert!(grid.number_of_stored_cubes() < 848 / 2);
+
+    let mut grid = Grid::<3>::new_from_input(&input);
+    grid.use_mirror_symmetry();
+    for _ in 1..=6 {
+        grid.tick();
+    }
+    assert_eq!(grid.number_of_active_cubes(), 112);
+}
+
+#[test]
+fn test_use_mirror_symmetry_needs_a_symmetric_grid() {
+    let mut grid = Grid::new();
+    grid.update_cube_at_point(&Point([0, 0, 1]), CubeStatus::Active);
+    assert!(!grid.use_mirror_symmetry());
+    assert_eq!(grid.number_of_stored_cubes(), 1);
+
+    grid.update_cube_at_point(&Point([0, 0, -1]), CubeStatus::Active);
+    assert!(grid.use_mirror_symmetry());
+    assert_eq!(grid.number_of_stored_cubes(), 1);
+    assert_eq!(grid.number_of_active_cubes(), 2);
+    assert_eq!(grid.point_to_cube_status(&Point([0, 0, -1])), CubeStatus::Active);
+
+    // Setting a cube sets its mirror image too
+    grid.update_cube_at_point(&Point([0, 0, -1]), CubeStatus::Inactive);
+    assert_eq!(grid.number_of_active_cubes(), 0);
+}
+
+#[test]
+fn test_parse_life_rule() {
+    assert_eq!(LifeRule::parse("B3/S23"), Ok(LifeRule::conway()));
+    assert_eq!(LifeRule::parse("b36/s32"), Ok(LifeRule { birth: vec![3, 6], survival: vec![2, 3] }));
+    assert_eq!(LifeRule::parse("B3/S"), Ok(LifeRule { birth: vec![3], survival: vec![] }));
+    assert_eq!(LifeRule::parse("B3,12/S2-4,20"), Ok(LifeRule { birth: vec![3, 12], survival: vec![2, 3, 4, 20] }));
+
+    assert_eq!(LifeRule::parse("B3S23"), Err(RuleError::InvalidNotation(String::from("B3S23"))));
+    assert_eq!(LifeRule::parse("S23/B3"), Err(RuleError::InvalidNotation(String::from("S23/B3"))));
+    assert_eq!(LifeRule::parse("B3/S2x"), Err(RuleError::InvalidCount(String::from("x"))));
+    assert_eq!(LifeRule::parse("B3/S4-2"), Err(RuleError::InvalidCount(String::from("4-2"))));
+    assert_eq!(LifeRule::parse("B03/S23"), Err(RuleError::BirthWithoutNeighbours));
+}
+
+#[test]
+fn test_display_life_rule() {
+    assert_eq!(LifeRule::conway().to_string(), "B3/S23");
+    assert_eq!(LifeRule::parse("B3,12/S2-4").unwrap().to_string(), "B3,12/S234");
+}
+
+#[test]
+fn test_life_rule_next_state() {
+    let rule = LifeRule::parse("B36/S23").unwrap();
+    assert_eq!(rule.next_state(CubeStatus::Inactive, 6), CubeStatus::Active);
+    assert_eq!(rule.next_state(CubeStatus::Inactive, 2), CubeStatus::Inactive);
+    assert_eq!(rule.next_state(CubeStatus::Active, 2), CubeStatus::Active);
+    assert_eq!(rule.next_state(CubeStatus::Active, 6), CubeStatus::Inactive);
+}
+
+#[test]
+fn test_grid_with_other_rule() {
+    // With B1/S every cube next to exactly one active cube comes alive, and none survive
+    let mut grid = Grid::new();
+    grid.set_rule(LifeRule::parse("B1/S").unwrap());
+    grid.update_cube_at_point(&Point([0, 0, 0]), CubeStatus::Active);
+    assert!(grid.use_mirror_symmetry());
+
+    grid.tick();
+    assert_eq!(grid.number_of_active_cubes(), 26);
+    assert_eq!(grid.point_to_cube_status(&Point([0, 0, 0])), CubeStatus::Inactive);
+    assert_eq!(grid.point_to_cube_status(&Point([1, -1, -1])), CubeStatus::Active);
+}
+
+#[test]
+fn test_grid_survival_without_neighbours() {
+    // With S0 a cube on its own survives, even though no active cube counts it as a neighbour
+    for symmetry in [true, false].iter() {
+        let mut grid = Grid::new();
+        grid.set_rule(LifeRule::parse("B3/S0").unwrap());
+        grid.update_cube_at_point(&Point([0, 0, 0]), CubeStatus::Active);
+        if *symmetry {
+            assert!(grid.use_mirror_symmetry());
+        }
+
+        grid.tick();
+        assert_eq!(grid.number_of_active_cubes(), 1);
+        assert_eq!(grid.point_to_cube_status(&Point([0, 0, 0])), CubeStatus::Active);
+    }
+}
+
+fn write_seed(name: &str, seed: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("day17_{}_{}.txt", name, std::process::id()));
+    fs::write(&path, seed).unwrap();
+    path
+}
+
+#[test]
+fn test_get_input() {
+    let path = write_seed("valid", ".#.\n..#\n###\n");
+    assert_eq!(get_input(path.to_str().unwrap()), Ok(vec![
+        String::from(".#."),
+        String::from("..#"),
+        String::from("###"),
+    ]));
+    fs::remove_file(&path).unwrap();
+
+    let path = write_seed("invalid", ".#.\n.x#\n");
+    assert_eq!(get_input(path.to_str().unwrap()),
+               Err(String::from("Line 2, column 2: Seeds can only contain # and ., not 'x'")));
+    fs::remove_file(&path).unwrap();
+
+    assert!(get_input("does/not/exist.txt").is_err());
+}
+
+#[test]
+fn test_path_for_dimensions() {
+    assert_eq!(path_for_dimensions("out/sheet.png", 4), PathBuf::from("out/sheet.4d.png"));
+}
+
+#[test]
+fn test_render_slice_sheet() {
+    let input = vec![
+        String::from(".#."),
+        String::from("..#"),
+        String::from("###"),
+    ];
+
+    // The extents are 5 x 5 x 3, so there's a row of 3 slices, each with a gutter around it
+    let grid = Grid::<3>::new_from_input(&input);
+    let sheet = render_slice_sheet(&grid, 2);
+    assert_eq!(sheet.dimensions(), ((3 * 6 + 1) * 2, (6 + 1) * 2));
+
+    // The middle slice is z = 0, and (1, 0) is active: the slice starts at x = -1, y = -1
+    let (left, top) = ((1 + 6 + 2) * 2, (1 + 1) * 2);
+    assert_ne!(sheet.get_pixel(left, top), sheet.get_pixel(left - 2, top));
+
+    // 4D sheets get a row per w
+    let grid = Grid::<4>::new_from_input(&input);
+    assert_eq!(render_slice_sheet(&grid, 1).dimensions(), (3 * 6 + 1, 3 * 6 + 1));
+
+    // Once every cube has died out there's nothing to lay out
+    let mut grid = Grid::<3>::new_from_input(&[String::from("#")]);
+    grid.tick();
+    assert_eq!(grid.number_of_active_cubes(), 0);
+    let sheet = render_slice_sheet(&grid, 2);
+    assert_eq!(sheet.dimensions(), (2, 2));
+    assert_eq!(render_slice_sheet(&Grid::<4>::new_from_input(&[]), 1).dimensions(), (1, 1));
+}
@@ -0,0 +1,196 @@
+use crate::lexer::Span;
+use core::fmt;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum Operator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Pow,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum EvaluationErrorKind {
+    Overflow,
+    DivisionByZero,
+    NegativeExponent,
+    UnknownVariable(String),
+}
+
+/// A problem found while evaluating an expression, pointing at the operator or variable that
+/// caused it.
+#[derive(Debug, PartialEq)]
+pub struct EvaluationError {
+    pub kind: EvaluationErrorKind,
+    pub span: Span,
+}
+
+impl EvaluationError {
+    /// The expression with the offending operator underlined, followed by the error.
+    pub fn show(&self, input: &str) -> String {
+        format!("{}\n{}", self.span.underline(input), self)
+    }
+}
+
+impl Display for EvaluationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Column {}: ", self.span.start + 1)?;
+        match &self.kind {
+            EvaluationErrorKind::Overflow => write!(f, "The result doesn't fit in 64 bits"),
+            EvaluationErrorKind::DivisionByZero => write!(f, "Division by zero"),
+            EvaluationErrorKind::NegativeExponent => write!(f, "Negative exponents don't give whole numbers"),
+            EvaluationErrorKind::UnknownVariable(name) => write!(f, "'{}' hasn't been given a value", name),
+        }
+    }
+}
+
+impl Operator {
+    pub const ALL: [Operator; 6] = [
+        Operator::Add, Operator::Sub, Operator::Mul, Operator::Div, Operator::Mod, Operator::Pow,
+    ];
+
+    pub fn from_symbol(symbol: char) -> Option<Operator> {
+        Operator::ALL.iter().copied().find(|operator| operator.symbol() == symbol)
+    }
+
+    pub fn symbol(&self) -> char {
+        match self {
+            Operator::Add => '+',
+            Operator::Sub => '-',
+            Operator::Mul => '*',
+            Operator::Div => '/',
+            Operator::Mod => '%',
+            Operator::Pow => '^',
+        }
+    }
+
+    /// Division rounds towards zero, and the remainder takes the sign of the left side to match.
+    pub fn apply(&self, op1: i64, op2: i64) -> Result<i64, EvaluationErrorKind> {
+        let result = match self {
+            Operator::Add => op1.checked_add(op2),
+            Operator::Sub => op1.checked_sub(op2),
+            Operator::Mul => op1.checked_mul(op2),
+            Operator::Div | Operator::Mod if op2 == 0 => return Err(EvaluationErrorKind::DivisionByZero),
+            Operator::Div => op1.checked_div(op2),
+            Operator::Mod => op1.checked_rem(op2),
+            Operator::Pow => return power(op1, op2),
+        };
+
+        result.ok_or(EvaluationErrorKind::Overflow)
+    }
+}
+
+fn power(base: i64, exponent: i64) -> Result<i64, EvaluationErrorKind> {
+    if exponent < 0 {
+        return Err(EvaluationErrorKind::NegativeExponent);
+    }
+
+    match (base, u32::try_from(exponent)) {
+        (_, Ok(exponent)) => base.checked_pow(exponent).ok_or(EvaluationErrorKind::Overflow),
+        // Only these survive exponents too large for checked_pow
+        (0, Err(_)) | (1, Err(_)) => Ok(base),
+        (-1, Err(_)) => Ok(if exponent % 2 == 0 { 1 } else { -1 }),
+        _ => Err(EvaluationErrorKind::Overflow),
+    }
+}
+
+impl Display for Operator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.symbol())
+    }
+}
+
+/// A parsed expression. Operations and variables keep their span, so problems with them can be
+/// pointed at.
+#[derive(Debug, PartialEq)]
+pub enum Expression {
+    Number(i64),
+    Variable {
+        name: String,
+        span: Span,
+    },
+    Negate {
+        span: Span,
+        operand: Box<Expression>,
+    },
+    Binary {
+        operator: Operator,
+        span: Span,
+        left: Box<Expression>,
+        right: Box<Expression>,
+    },
+}
+
+impl Expression {
+    pub fn negate(span: Span, operand: Expression) -> Expression {
+        Expression::Negate { span, operand: Box::new(operand) }
+    }
+
+    pub fn binary(operator: Operator, span: Span, left: Expression, right: Expression) -> Expression {
+        Expression::Binary {
+            operator,
+            span,
+            left: Box::new(left),
+            right: Box::new(right),
+        }
+    }
+
+    pub fn evaluate(&self) -> Result<i64, EvaluationError> {
+        self.evaluate_with(&HashMap::new())
+    }
+
+    pub fn evaluate_with(&self, variables: &HashMap<String, i64>) -> Result<i64, EvaluationError> {
+        match self {
+            Expression::Number(value) => Ok(*value),
+            Expression::Variable { name, span } => variables.get(name)
+                .copied()
+                .ok_or_else(|| EvaluationError { kind: EvaluationErrorKind::UnknownVariable(name.clone()), span: *span }),
+            Expression::Negate { span, operand } => operand.evaluate_with(variables)?
+                .checked_neg()
+                .ok_or(EvaluationError { kind: EvaluationErrorKind::Overflow, span: *span }),
+            Expression::Binary { operator, span, left, right } => operator
+                .apply(left.evaluate_with(variables)?, right.evaluate_with(variables)?)
+                .map_err(|kind| EvaluationError { kind, span: *span }),
+        }
+    }
+
+    /// The expression in reverse Polish notation, with `neg` for negation: `-(1 + 2) * 3` is
+    /// `1 2 + neg 3 *`.
+    pub fn to_rpn(&self) -> String {
+        match self {
+            Expression::Number(value) => value.to_string(),
+            Expression::Variable { name, .. } => name.clone(),
+            Expression::Negate { operand, .. } => format!("{} neg", operand.to_rpn()),
+            Expression::Binary { operator, left, right, .. } => {
+                format!("{} {} {}", left.to_rpn(), right.to_rpn(), operator)
+            }
+        }
+    }
+}
+
+/// Shows the expression with every operation in parentheses, so the grouping the precedence
+/// table picked is visible.
+impl Display for Expression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Expression::Number(value) => write!(f, "{}", value),
+            Expression::Variable { name, .. } => write!(f, "{}", name),
+            Expression::Negate { operand, .. } => write!(f, "(-{})", operand),
+            Expression::Binary { operator, left, right, .. } => write!(f, "({} {} {})", left, operator, right),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Statement {
+    Expression(Expression),
+    Assignment {
+        name: String,
+        expression: Expression,
+    },
+}
@@ -0,0 +1,57 @@
+use std::env;
+use std::io::{self, BufRead, Write};
+use std::process;
+use day18::precedence::PrecedenceTable;
+use day18::repl::{Reply, Session};
+
+const USAGE: &str = "Usage: repl [--precedence equal|addition-first|standard|TABLE FILE]";
+
+fn parse_args(args: &[String]) -> Result<PrecedenceTable, String> {
+    let mut table = PrecedenceTable::standard();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match &arg[..] {
+            "--precedence" => {
+                let value = args.next()
+                    .ok_or_else(|| String::from("--precedence expects a preset name or a table file"))?;
+                table = PrecedenceTable::load(value)?;
+            }
+            _ => return Err(format!("Unexpected argument {}", arg)),
+        }
+    }
+
+    Ok(table)
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let table = match parse_args(&args) {
+        Ok(table) => table,
+        Err(error) => {
+            eprintln!("{}\n{}", error, USAGE);
+            process::exit(1);
+        }
+    };
+
+    let mut session = Session::new(table);
+    println!("Type :help for the commands, :quit to leave");
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("> ");
+        io::stdout().flush().unwrap();
+
+        let line = match lines.next() {
+            Some(line) => line.unwrap(),
+            None => break,
+        };
+
+        match session.handle(&line) {
+            Reply::Output(output) if output.is_empty() => {}
+            Reply::Output(output) => println!("{}", output),
+            Reply::Quit => break,
+        }
+    }
+}
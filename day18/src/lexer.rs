@@ -0,0 +1,131 @@
+use crate::ast::Operator;
+use core::fmt;
+use std::fmt::{Display, Formatter};
+
+/// Where something is in the input, as 0-based character columns. `end` isn't included.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+
+    /// The input, with the span marked on the line below it.
+    pub fn underline(&self, input: &str) -> String {
+        let width = (self.end - self.start).max(1);
+        format!("{}\n{}{}", input, " ".repeat(self.start), "^".repeat(width))
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum TokenKind {
+    Number(i64),
+    Identifier(String),
+    Operator(Operator),
+    LeftParenthesis,
+    RightParenthesis,
+    Equals,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SyntaxErrorKind {
+    UnexpectedCharacter(char),
+    NumberTooLarge,
+    ExpectedOperand,
+    ExpectedOperator,
+    UnclosedParenthesis,
+    UnexpectedEnd,
+    OperatorNotInTable(Operator),
+    NotPrefixOperator(Operator),
+}
+
+/// A problem with an expression, and where in it the problem is.
+#[derive(Debug, PartialEq)]
+pub struct SyntaxError {
+    pub kind: SyntaxErrorKind,
+    pub span: Span,
+}
+
+impl SyntaxError {
+    /// The expression with the offending part underlined, followed by the error.
+    pub fn show(&self, input: &str) -> String {
+        format!("{}\n{}", self.span.underline(input), self)
+    }
+}
+
+impl Display for SyntaxError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Column {}: ", self.span.start + 1)?;
+        match self.kind {
+            SyntaxErrorKind::UnexpectedCharacter(c) => write!(f, "Unexpected character '{}'", c),
+            SyntaxErrorKind::NumberTooLarge => write!(f, "Number is too large"),
+            SyntaxErrorKind::ExpectedOperand => write!(f, "Expected a number or a parenthesis"),
+            SyntaxErrorKind::ExpectedOperator => write!(f, "Expected an operator"),
+            SyntaxErrorKind::UnclosedParenthesis => write!(f, "This parenthesis is never closed"),
+            SyntaxErrorKind::UnexpectedEnd => write!(f, "Unexpected end of expression"),
+            SyntaxErrorKind::OperatorNotInTable(operator) => {
+                write!(f, "The precedence table doesn't have an entry for '{}'", operator)
+            }
+            SyntaxErrorKind::NotPrefixOperator(operator) => {
+                write!(f, "'{}' needs something on its left", operator)
+            }
+        }
+    }
+}
+
+/// Splits an expression into tokens. Whitespace is only needed between numbers and names.
+pub fn tokenize(input: &str) -> Result<Vec<Token>, SyntaxError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = vec![];
+
+    let mut i = 0;
+    while i < chars.len() {
+        let start = i;
+        i += 1;
+
+        let kind = match chars[start] {
+            c if c.is_whitespace() => continue,
+            c if c.is_ascii_digit() => {
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+
+                let number: String = chars[start..i].iter().collect();
+                let value = number.parse::<i64>()
+                    .map_err(|_| SyntaxError { kind: SyntaxErrorKind::NumberTooLarge, span: Span::new(start, i) })?;
+                TokenKind::Number(value)
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+
+                TokenKind::Identifier(chars[start..i].iter().collect())
+            }
+            '(' => TokenKind::LeftParenthesis,
+            ')' => TokenKind::RightParenthesis,
+            '=' => TokenKind::Equals,
+            c => match Operator::from_symbol(c) {
+                Some(operator) => TokenKind::Operator(operator),
+                None => {
+                    let kind = SyntaxErrorKind::UnexpectedCharacter(c);
+                    return Err(SyntaxError { kind, span: Span::new(start, i) });
+                }
+            },
+        };
+
+        tokens.push(Token { kind, span: Span::new(start, i) });
+    }
+
+    Ok(tokens)
+}
@@ -0,0 +1,53 @@
+use crate::ast::EvaluationError;
+use crate::lexer::SyntaxError;
+use crate::parser::parse;
+use crate::precedence::PrecedenceTable;
+
+pub mod ast;
+pub mod lexer;
+pub mod parser;
+pub mod precedence;
+pub mod repl;
+#[cfg(test)]
+mod tests;
+
+#[derive(Debug, PartialEq)]
+pub enum CalculationError {
+    Syntax(SyntaxError),
+    Evaluation(EvaluationError),
+}
+
+impl CalculationError {
+    pub fn show(&self, input: &str) -> String {
+        match self {
+            CalculationError::Syntax(error) => error.show(input),
+            CalculationError::Evaluation(error) => error.show(input),
+        }
+    }
+}
+
+impl From<SyntaxError> for CalculationError {
+    fn from(error: SyntaxError) -> Self {
+        CalculationError::Syntax(error)
+    }
+}
+
+impl From<EvaluationError> for CalculationError {
+    fn from(error: EvaluationError) -> Self {
+        CalculationError::Evaluation(error)
+    }
+}
+
+/// Evaluates with every operator applied left to right.
+pub fn calculate(expression: &str) -> Result<i64, CalculationError> {
+    calculate_with(expression, &PrecedenceTable::equal())
+}
+
+/// Evaluates with addition applied before multiplication.
+pub fn calculate2(expression: &str) -> Result<i64, CalculationError> {
+    calculate_with(expression, &PrecedenceTable::addition_first())
+}
+
+pub fn calculate_with(expression: &str, table: &PrecedenceTable) -> Result<i64, CalculationError> {
+    Ok(parse(expression, table)?.evaluate()?)
+}
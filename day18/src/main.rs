@@ -1,286 +1,93 @@
 use std::path::Path;
 use std::fs::File;
 use std::io::{BufReader, BufRead};
-use std::cmp::Ordering;
+use std::env;
+use std::process;
+use day18::calculate_with;
+use day18::precedence::PrecedenceTable;
 
-mod tests;
+const USAGE: &str = "Usage: day18 [--precedence equal|addition-first|standard|TABLE FILE] [input file]";
 
-#[derive(Debug, PartialEq, Eq)]
-enum Operator {
-    Add,
-    Sub,
-    Mul,
-    Div,
-}
-
-impl Ord for Operator {
-    fn cmp(&self, other: &Self) -> Ordering {
-        match self {
-            Operator::Add => match other {
-                Operator::Mul => Ordering::Greater,
-                Operator::Div => Ordering::Greater,
-                Operator::Add => Ordering::Equal,
-                Operator::Sub => Ordering::Equal
-            },
-            Operator::Sub => match other {
-                Operator::Mul => Ordering::Greater,
-                Operator::Div => Ordering::Greater,
-                Operator::Add => Ordering::Equal,
-                Operator::Sub => Ordering::Equal
-            },
-            Operator::Mul => match other {
-                Operator::Mul => Ordering::Equal,
-                Operator::Div => Ordering::Equal,
-                Operator::Add => Ordering::Less,
-                Operator::Sub => Ordering::Less
-            },
-            Operator::Div => match other {
-                Operator::Mul => Ordering::Equal,
-                Operator::Div => Ordering::Equal,
-                Operator::Add => Ordering::Less,
-                Operator::Sub => Ordering::Less
-            },
-        }
-    }
-}
-
-impl PartialOrd for Operator {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
-}
-
-impl Operator {
-    pub fn apply(&self, op1: i64, op2: i64) -> i64 {
-        match self {
-            Operator::Add => op1 + op2,
-            Operator::Sub => op1 - op2,
-            Operator::Mul => op1 * op2,
-            Operator::Div => op1 / op2
-        }
-    }
-}
-
-#[derive(Debug, PartialEq)]
-enum Token {
-    Operator(Operator),
-    Operand(i64),
-    ParenthesisGroup(Vec<Token>),
-    ParenthesisedToken(String),
-}
-
-/*
-Returns the next token and the left over string
- */
-fn next_token(input: &str) -> (Token, &str) {
-    let input = input.trim();
-
-    if input.starts_with("(") {
-        // Remove the starting parenthesis
-        let input = &input[1..];
-
-        let mut parenthesised_group = String::new();
-
-        let mut num_parens = 1;
-        for c in input.chars() {
-            match c {
-                ')' => {
-                    num_parens -= 1;
-
-                    if num_parens == 0 {
-                        break;
-                    }
-                }
-                '(' => {
-                    num_parens += 1;
-                }
-                _ => {}
-            }
-
-            parenthesised_group.push(c);
-        }
+fn get_input(filename: &str) -> Vec<String> {
+    let p = Path::new(filename);
+    let f = File::open(p).unwrap();
+    let lines = BufReader::new(f).lines();
 
-        // Remove the group we just parsed
-        let left_over = input.strip_prefix(&parenthesised_group).unwrap();
-        // Remove the ending parent for that group
-        let left_over = &left_over[1..];
-        // Remove any left over white space after the group ends
-        let left_over = left_over.trim();
+    let mut input = vec![];
 
-        return (Token::ParenthesisedToken(parenthesised_group), left_over);
+    for l in lines {
+        input.push(l.unwrap());
     }
 
-    let split: Vec<&str> = input.splitn(2, " ").collect();
-    let token_str = split[0];
-    let left_over = if split.len() == 2 {
-        split[1]
-    } else {
-        ""
-    };
-
-    let token = match token_str {
-        "+" => Token::Operator(Operator::Add),
-        "-" => Token::Operator(Operator::Sub),
-        "*" => Token::Operator(Operator::Mul),
-        "/" => Token::Operator(Operator::Div),
-        other => Token::Operand(
-            other.parse::<i64>().unwrap()
-        )
-    };
-
-    (token, left_over)
+    input
 }
 
-fn parse(input: &str) -> Vec<Token> {
-    let mut input = input;
-    let mut output = vec![];
-
-    loop {
-        let (token, left_over) = next_token(input);
-        match token {
-            Token::ParenthesisedToken(new_input) => {
-                output.push(
-                    Token::ParenthesisGroup(parse(&new_input))
-                );
-            }
-            t => {
-                output.push(t);
-            }
-        }
-
-        if left_over.is_empty() {
-            break;
-        }
+/// Adds up every expression, stopping at the first one that can't be calculated.
+fn sum_all(input: &[String], table: &PrecedenceTable) -> Result<i64, String> {
+    let mut sum: i64 = 0;
 
-        input = left_over;
+    for (i, expression) in input.iter().enumerate() {
+        let value = calculate_with(expression, table)
+            .map_err(|error| format!("Line {}:\n{}", i + 1, error.show(expression)))?;
+        sum = sum.checked_add(value)
+            .ok_or_else(|| format!("Line {}: The sum doesn't fit in 64 bits", i + 1))?;
     }
 
-    output
+    Ok(sum)
 }
 
-fn value_of(token: &Token, calculate: fn(&Vec<Token>) -> i64) -> i64 {
-    match token {
-        Token::Operand(v) => *v,
-        Token::ParenthesisGroup(v) => calculate(v),
-        _ => {
-            panic!("This shouldn't happen.");
-        }
-    }
+struct Options {
+    filename: String,
+    table: Option<PrecedenceTable>,
 }
 
-fn calculate(input: &Vec<Token>) -> i64 {
-    let mut input_iter = input.iter();
-
-    let mut first_operand = value_of(input_iter.next().unwrap(), calculate);
-    let mut operator = input_iter.next().unwrap();
-    let mut second_operand = value_of(input_iter.next().unwrap(), calculate);
-
-    if let Token::Operator(op) = operator {
-        first_operand = op.apply(first_operand, second_operand);
-    }
-
-    for token in input_iter {
-        match token {
-            Token::Operator(_) => {
-                operator = token;
-            }
-            _ => {
-                if let Token::Operator(op) = operator {
-                    first_operand = op.apply(first_operand, value_of(token, calculate));
-                }
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut filename = String::from("input.txt");
+    let mut table = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match &arg[..] {
+            "--precedence" => {
+                let value = args.next()
+                    .ok_or_else(|| String::from("--precedence expects a preset name or a table file"))?;
+                table = Some(PrecedenceTable::load(value)?);
             }
+            _ => filename = arg.clone(),
         }
     }
 
-    first_operand
+    Ok(Options { filename, table })
 }
 
-fn calculate2(input: &Vec<Token>) -> i64 {
-    let mut output_stack: Vec<&Token> = vec![];
-    let mut operator_stack: Vec<&Token> = vec![];
-
-    for token in input.iter() {
-        match token {
-            Token::Operator(op) => {
-                while !operator_stack.is_empty() {
-                    let other_op_token = *operator_stack.last().unwrap();
-                    if let Token::Operator(other_op) = other_op_token {
-                        if other_op > op {
-                            output_stack.push(
-                                operator_stack.pop().unwrap()
-                            );
-                        } else {
-                            break;
-                        }
-                    }
-                }
-
-                operator_stack.push(token);
-            }
-            _ => {
-                output_stack.push(token);
-            }
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}\n{}", error, USAGE);
+            process::exit(1);
         }
-    }
+    };
 
-    while !operator_stack.is_empty() {
-        output_stack.push(
-            operator_stack.pop().unwrap()
-        );
-    }
+    let input = get_input(&options.filename);
+
+    // Without a table of our own, solve both parts of the puzzle
+    let runs = match options.table {
+        Some(table) => vec![(String::from("Sum"), table)],
+        None => vec![
+            (String::from("Part 1"), PrecedenceTable::equal()),
+            (String::from("Part 2"), PrecedenceTable::addition_first()),
+        ],
+    };
 
-    let mut operand_stack: Vec<i64> = vec![];
-    for token in output_stack {
-        match token {
-            Token::Operator(op) => {
-                let op1 = operand_stack.pop().unwrap();
-                let op2 = operand_stack.pop().unwrap();
-                operand_stack.push(
-                    op.apply(op1, op2)
-                );
-            },
-            _ => {
-                operand_stack.push(
-                    value_of(token, calculate2)
-                );
+    for (label, table) in runs {
+        match sum_all(&input, &table) {
+            Ok(sum) => println!("{}: {}", label, sum),
+            Err(error) => {
+                eprintln!("{}", error);
+                process::exit(1);
             }
         }
     }
-
-    operand_stack.pop().unwrap()
-}
-
-fn get_input(filename: &str) -> Vec<String> {
-    let p = Path::new(filename);
-    let f = File::open(p).unwrap();
-    let lines = BufReader::new(f).lines();
-
-    let mut input = vec![];
-
-    for l in lines {
-        input.push(l.unwrap());
-    }
-
-    input
-}
-
-fn main() {
-    let input = get_input("input.txt");
-    let mut sum = 0;
-
-    for expression in input.iter() {
-        let parsed_expression = parse(expression.as_str());
-        sum += calculate(&parsed_expression);
-    }
-
-    println!("Part 1: {}", sum);
-
-    let mut sum = 0;
-
-    for expression in input.iter() {
-        let parsed_expression = parse(expression.as_str());
-        sum += calculate2(&parsed_expression);
-    }
-
-    println!("Part 2: {}", sum);
 }
@@ -0,0 +1,110 @@
+use crate::ast::{Expression, Operator, Statement};
+use crate::lexer::{tokenize, Span, SyntaxError, SyntaxErrorKind, Token, TokenKind};
+use crate::precedence::PrecedenceTable;
+
+/// A Pratt parser over the tokens of a single statement.
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    position: usize,
+    table: &'a PrecedenceTable,
+    // Where to point errors about the expression ending too early
+    end: Span,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<Token> {
+        self.tokens.get(self.position).cloned()
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek();
+        self.position += 1;
+        token
+    }
+
+    fn error(kind: SyntaxErrorKind, span: Span) -> SyntaxError {
+        SyntaxError { kind, span }
+    }
+
+    /// Parses operators binding at least as tightly as `min_binding_power`.
+    fn expression(&mut self, min_binding_power: u16) -> Result<Expression, SyntaxError> {
+        let mut left = self.operand()?;
+
+        while let Some(Token { kind: TokenKind::Operator(operator), span }) = self.peek() {
+            let (left_binding_power, right_binding_power) = self.table.binding_power(operator)
+                .ok_or_else(|| Parser::error(SyntaxErrorKind::OperatorNotInTable(operator), span))?;
+            if left_binding_power < min_binding_power {
+                break;
+            }
+
+            self.next();
+            let right = self.expression(right_binding_power)?;
+            left = Expression::binary(operator, span, left, right);
+        }
+
+        Ok(left)
+    }
+
+    fn operand(&mut self) -> Result<Expression, SyntaxError> {
+        match self.next() {
+            Some(Token { kind: TokenKind::Number(value), .. }) => Ok(Expression::Number(value)),
+            Some(Token { kind: TokenKind::Identifier(name), span }) => Ok(Expression::Variable { name, span }),
+            Some(Token { kind: TokenKind::LeftParenthesis, span: opening }) => {
+                let inner = self.expression(0)?;
+                match self.next() {
+                    Some(Token { kind: TokenKind::RightParenthesis, .. }) => Ok(inner),
+                    Some(token) => Err(Parser::error(SyntaxErrorKind::ExpectedOperator, token.span)),
+                    None => Err(Parser::error(SyntaxErrorKind::UnclosedParenthesis, opening)),
+                }
+            }
+            Some(Token { kind: TokenKind::Operator(Operator::Sub), span }) => {
+                let operand = self.expression(self.table.prefix_binding_power(Operator::Sub))?;
+                Ok(Expression::negate(span, operand))
+            }
+            Some(Token { kind: TokenKind::Operator(operator), span }) => {
+                Err(Parser::error(SyntaxErrorKind::NotPrefixOperator(operator), span))
+            }
+            Some(token) => Err(Parser::error(SyntaxErrorKind::ExpectedOperand, token.span)),
+            None => Err(Parser::error(SyntaxErrorKind::UnexpectedEnd, self.end)),
+        }
+    }
+
+    /// Parses an expression that has to take up the rest of the input.
+    fn whole_expression(&mut self) -> Result<Expression, SyntaxError> {
+        let expression = self.expression(0)?;
+        match self.peek() {
+            // Anything left over can only be an operand without an operator before it
+            Some(token) => Err(Parser::error(SyntaxErrorKind::ExpectedOperator, token.span)),
+            None => Ok(expression),
+        }
+    }
+}
+
+fn parser_for<'a>(input: &str, table: &'a PrecedenceTable) -> Result<Parser<'a>, SyntaxError> {
+    let length = input.chars().count();
+    Ok(Parser {
+        tokens: tokenize(input)?,
+        position: 0,
+        table,
+        end: Span::new(length, length + 1),
+    })
+}
+
+/// Parses an expression, applying operators in the order the precedence table says.
+pub fn parse(input: &str, table: &PrecedenceTable) -> Result<Expression, SyntaxError> {
+    parser_for(input, table)?.whole_expression()
+}
+
+/// Parses either an expression or an assignment like `x = 3 + 4`.
+pub fn parse_statement(input: &str, table: &PrecedenceTable) -> Result<Statement, SyntaxError> {
+    let mut parser = parser_for(input, table)?;
+
+    match (parser.tokens.first(), parser.tokens.get(1)) {
+        (Some(Token { kind: TokenKind::Identifier(name), .. }), Some(Token { kind: TokenKind::Equals, .. })) => {
+            let name = name.clone();
+            parser.position = 2;
+            Ok(Statement::Assignment { name, expression: parser.whole_expression()? })
+        }
+        _ => Ok(Statement::Expression(parser.whole_expression()?)),
+    }
+}
@@ -0,0 +1,215 @@
+use crate::ast::Operator;
+use core::fmt;
+use std::fmt::{Display, Formatter};
+use std::fs;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Associativity {
+    Left,
+    Right,
+    /// Unary operators written before their operand, like the minus in `-3`.
+    Prefix,
+}
+
+/// Operators that bind equally tightly, and the order to apply them in when they're chained.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Level {
+    pub associativity: Associativity,
+    pub operators: Vec<Operator>,
+}
+
+/// How tightly each operator binds, as a list of levels from the tightest binding to the loosest.
+///
+/// Tables are written one level per line, the associativity followed by the operators on it.
+/// Blank lines and lines starting with `#` are ignored:
+///
+/// ```text
+/// right ^
+/// prefix -
+/// left * / %
+/// left + -
+/// ```
+///
+/// Negation binds tighter than everything else unless the table has a `prefix` level for it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct PrecedenceTable {
+    levels: Vec<Level>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TableError {
+    UnknownAssociativity(String),
+    UnknownOperator(String),
+    EmptyLevel,
+    DuplicateOperator(Operator),
+    NotPrefixOperator(Operator),
+}
+
+impl Display for TableError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TableError::UnknownAssociativity(word) => {
+                write!(f, "Expected left, right or prefix at the start of the level, got '{}'", word)
+            }
+            TableError::UnknownOperator(word) => write!(f, "'{}' isn't an operator", word),
+            TableError::EmptyLevel => write!(f, "The level has no operators"),
+            TableError::DuplicateOperator(operator) => write!(f, "'{}' is already on an earlier level", operator),
+            TableError::NotPrefixOperator(operator) => write!(f, "'{}' can't be used as a prefix", operator),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct TableParseError {
+    pub line: usize,
+    pub error: TableError,
+}
+
+impl Display for TableParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Line {}: {}", self.line, self.error)
+    }
+}
+
+impl PrecedenceTable {
+    pub fn new(levels: Vec<Level>) -> PrecedenceTable {
+        PrecedenceTable { levels }
+    }
+
+    pub fn parse(input: &str) -> Result<PrecedenceTable, TableParseError> {
+        let mut levels: Vec<Level> = vec![];
+
+        for (i, line) in input.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let error = |error| TableParseError { line: i + 1, error };
+            let mut words = line.split_whitespace();
+
+            let associativity = match words.next() {
+                Some("left") => Associativity::Left,
+                Some("right") => Associativity::Right,
+                Some("prefix") => Associativity::Prefix,
+                Some(word) => return Err(error(TableError::UnknownAssociativity(word.to_string()))),
+                None => unreachable!("blank lines are skipped"),
+            };
+
+            let mut operators = vec![];
+            for word in words {
+                let mut symbols = word.chars();
+                let operator = match (symbols.next().and_then(Operator::from_symbol), symbols.next()) {
+                    (Some(operator), None) => operator,
+                    _ => return Err(error(TableError::UnknownOperator(word.to_string()))),
+                };
+
+                let is_prefix = associativity == Associativity::Prefix;
+                if is_prefix && operator != Operator::Sub {
+                    return Err(error(TableError::NotPrefixOperator(operator)));
+                }
+
+                // An operator can be listed once as a prefix and once between operands
+                let already_listed = operators.contains(&operator) || levels.iter()
+                    .filter(|level| (level.associativity == Associativity::Prefix) == is_prefix)
+                    .any(|level| level.operators.contains(&operator));
+                if already_listed {
+                    return Err(error(TableError::DuplicateOperator(operator)));
+                }
+                operators.push(operator);
+            }
+
+            if operators.is_empty() {
+                return Err(error(TableError::EmptyLevel));
+            }
+            levels.push(Level { associativity, operators });
+        }
+
+        Ok(PrecedenceTable::new(levels))
+    }
+
+    pub fn preset(name: &str) -> Option<PrecedenceTable> {
+        match name {
+            "equal" => Some(PrecedenceTable::equal()),
+            "addition-first" => Some(PrecedenceTable::addition_first()),
+            "standard" => Some(PrecedenceTable::standard()),
+            _ => None,
+        }
+    }
+
+    /// A preset by name, or else a table read from a file.
+    pub fn load(name_or_path: &str) -> Result<PrecedenceTable, String> {
+        if let Some(table) = PrecedenceTable::preset(name_or_path) {
+            return Ok(table);
+        }
+
+        let contents = fs::read_to_string(name_or_path)
+            .map_err(|error| format!("Couldn't read precedence table {}: {}", name_or_path, error))?;
+        PrecedenceTable::parse(&contents).map_err(|error| format!("{}: {}", name_or_path, error))
+    }
+
+    /// Part 1: everything is applied left to right.
+    pub fn equal() -> PrecedenceTable {
+        PrecedenceTable::parse("left + - * / % ^").unwrap()
+    }
+
+    /// Part 2: addition and subtraction are applied before multiplication and division.
+    pub fn addition_first() -> PrecedenceTable {
+        PrecedenceTable::parse("right ^\nleft + -\nleft * / %").unwrap()
+    }
+
+    /// The usual order from school, with `2 ^ 3 ^ 2` meaning `2 ^ (3 ^ 2)` and `-2 ^ 2` meaning
+    /// `-(2 ^ 2)`.
+    pub fn standard() -> PrecedenceTable {
+        PrecedenceTable::parse("right ^\nprefix -\nleft * / %\nleft + -").unwrap()
+    }
+
+    /// How strongly the operator holds on to the expressions on its left and right. The side
+    /// that binds a little tighter decides the associativity. `None` if the table doesn't list
+    /// the operator.
+    pub fn binding_power(&self, operator: Operator) -> Option<(u16, u16)> {
+        let index = self.levels.iter().position(|level| {
+            level.associativity != Associativity::Prefix && level.operators.contains(&operator)
+        })?;
+
+        let power = self.level_power(index);
+        match self.levels[index].associativity {
+            Associativity::Right => Some((power + 1, power)),
+            _ => Some((power, power + 1)),
+        }
+    }
+
+    /// How strongly a prefix operator holds on to the expression on its right.
+    pub fn prefix_binding_power(&self, operator: Operator) -> u16 {
+        self.levels.iter()
+            .position(|level| level.associativity == Associativity::Prefix && level.operators.contains(&operator))
+            .map_or(u16::MAX, |index| self.level_power(index))
+    }
+
+    // Tighter levels come first, but need the higher binding power
+    fn level_power(&self, index: usize) -> u16 {
+        2 * (self.levels.len() - index) as u16
+    }
+}
+
+impl Display for PrecedenceTable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (i, level) in self.levels.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+
+            let associativity = match level.associativity {
+                Associativity::Left => "left",
+                Associativity::Right => "right",
+                Associativity::Prefix => "prefix",
+            };
+            write!(f, "{}", associativity)?;
+            for operator in &level.operators {
+                write!(f, " {}", operator)?;
+            }
+        }
+
+        Ok(())
+    }
+}
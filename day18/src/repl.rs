@@ -0,0 +1,100 @@
+use crate::ast::Statement;
+use crate::parser::{parse, parse_statement};
+use crate::precedence::PrecedenceTable;
+use std::collections::HashMap;
+
+const HELP: &str = "\
+Enter an expression to evaluate it, or NAME = EXPRESSION to store its value in a variable.
+:precedence               Show the current precedence table
+:precedence PRESET|FILE   Switch to equal, addition-first, standard or a table file
+:tree EXPRESSION          Show how the expression groups, fully parenthesised and in RPN
+:vars                     List the variables
+:help                     Show this help
+:quit                     Leave";
+
+pub enum Reply {
+    Output(String),
+    Quit,
+}
+
+/// The state of an interactive session: the precedence table in use and the variables assigned
+/// so far.
+pub struct Session {
+    table: PrecedenceTable,
+    variables: HashMap<String, i64>,
+}
+
+impl Session {
+    pub fn new(table: PrecedenceTable) -> Session {
+        Session { table, variables: HashMap::new() }
+    }
+
+    /// Runs one line of input. Errors are part of the output, since the session carries on.
+    pub fn handle(&mut self, line: &str) -> Reply {
+        let line = line.trim();
+
+        if line.is_empty() {
+            Reply::Output(String::new())
+        } else if let Some(command) = line.strip_prefix(':') {
+            self.command(command)
+        } else {
+            Reply::Output(self.statement(line))
+        }
+    }
+
+    fn statement(&mut self, input: &str) -> String {
+        let statement = match parse_statement(input, &self.table) {
+            Ok(statement) => statement,
+            Err(error) => return error.show(input),
+        };
+
+        match statement {
+            Statement::Expression(expression) => match expression.evaluate_with(&self.variables) {
+                Ok(value) => value.to_string(),
+                Err(error) => error.show(input),
+            },
+            Statement::Assignment { name, expression } => match expression.evaluate_with(&self.variables) {
+                Ok(value) => {
+                    self.variables.insert(name.clone(), value);
+                    format!("{} = {}", name, value)
+                }
+                Err(error) => error.show(input),
+            },
+        }
+    }
+
+    fn command(&mut self, command: &str) -> Reply {
+        let (name, argument) = match command.find(char::is_whitespace) {
+            Some(i) => (&command[..i], command[i..].trim()),
+            None => (command, ""),
+        };
+
+        let output = match name {
+            "quit" | "q" => return Reply::Quit,
+            "help" => String::from(HELP),
+            "precedence" if argument.is_empty() => self.table.to_string(),
+            "precedence" => match PrecedenceTable::load(argument) {
+                Ok(table) => {
+                    self.table = table;
+                    self.table.to_string()
+                }
+                Err(error) => error,
+            },
+            "tree" => match parse(argument, &self.table) {
+                Ok(expression) => format!("{}\n{}", expression, expression.to_rpn()),
+                Err(error) => error.show(argument),
+            },
+            "vars" => {
+                let mut variables: Vec<_> = self.variables.iter().collect();
+                variables.sort();
+                variables.iter()
+                    .map(|(name, value)| format!("{} = {}", name, value))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+            _ => format!("Unknown command :{}, try :help", name),
+        };
+
+        Reply::Output(output)
+    }
+}
@@ -1,167 +1,409 @@
 use crate::*;
+use crate::ast::{EvaluationError, EvaluationErrorKind, Expression, Operator, Statement};
+use crate::parser::parse_statement;
+use crate::repl::{Reply, Session};
+use std::collections::HashMap;
+use crate::lexer::{tokenize, Span, SyntaxErrorKind, Token, TokenKind};
+use crate::precedence::{TableError, TableParseError};
+
+fn token(kind: TokenKind, start: usize, end: usize) -> Token {
+    Token { kind, span: Span::new(start, end) }
+}
+
+fn number(value: i64) -> Expression {
+    Expression::Number(value)
+}
+
+fn binary(operator: Operator, column: usize, left: Expression, right: Expression) -> Expression {
+    Expression::binary(operator, Span::new(column, column + 1), left, right)
+}
 
 #[test]
-fn test_next_token() {
+fn test_tokenize() {
+    itertools::assert_equal(tokenize("3 + 4").unwrap(), vec![
+        token(TokenKind::Number(3), 0, 1),
+        token(TokenKind::Operator(Operator::Add), 2, 3),
+        token(TokenKind::Number(4), 4, 5),
+    ]);
+
+    itertools::assert_equal(tokenize("(1+2)*34").unwrap(), vec![
+        token(TokenKind::LeftParenthesis, 0, 1),
+        token(TokenKind::Number(1), 1, 2),
+        token(TokenKind::Operator(Operator::Add), 2, 3),
+        token(TokenKind::Number(2), 3, 4),
+        token(TokenKind::RightParenthesis, 4, 5),
+        token(TokenKind::Operator(Operator::Mul), 5, 6),
+        token(TokenKind::Number(34), 6, 8),
+    ]);
+
+    assert_eq!(tokenize("  12 -\t6 / 3").unwrap().len(), 5);
+    assert!(tokenize("").unwrap().is_empty());
+
+    let error = tokenize("1 + $").unwrap_err();
+    assert_eq!(error.kind, SyntaxErrorKind::UnexpectedCharacter('$'));
+    assert_eq!(error.span, Span::new(4, 5));
+
+    let error = tokenize("1 + 99999999999999999999").unwrap_err();
+    assert_eq!(error.kind, SyntaxErrorKind::NumberTooLarge);
+    assert_eq!(error.span, Span::new(4, 24));
+}
+
+#[test]
+fn test_parse() {
+    assert_eq!(
+        parse("3 + 4", &PrecedenceTable::equal()),
+        Ok(binary(Operator::Add, 2, number(3), number(4)))
+    );
+
+    // 1 + 2 * 3: left to right, or addition first, both group the same way
+    let expected = binary(Operator::Mul, 3, binary(Operator::Add, 1, number(1), number(2)), number(3));
+    assert_eq!(parse("1+2*3", &PrecedenceTable::equal()), Ok(expected));
+
+    // 2 * 3 + 4 only groups differently when addition comes first
     assert_eq!(
-        next_token("3 + 4"), (Token::Operand(3), "+ 4")
+        parse("2 * 3 + 4", &PrecedenceTable::equal()),
+        Ok(binary(Operator::Add, 6, binary(Operator::Mul, 2, number(2), number(3)), number(4)))
     );
     assert_eq!(
-        next_token("+ 4"), (Token::Operator(Operator::Add), "4")
+        parse("2 * 3 + 4", &PrecedenceTable::addition_first()),
+        Ok(binary(Operator::Mul, 2, number(2), binary(Operator::Add, 6, number(3), number(4))))
     );
+
+    // Operators on the same level are left associative
     assert_eq!(
-        next_token("4"), (Token::Operand(4), "")
+        parse("8 - 4 - 2", &PrecedenceTable::equal()),
+        Ok(binary(Operator::Sub, 6, binary(Operator::Sub, 2, number(8), number(4)), number(2)))
     );
 
-    let mut input = "1 + 2 * 3 + 4 * 5 + 6";
-    let mut tokens = vec![];
-    loop {
-        let (token, left_over) = next_token(input);
-        tokens.push(token);
+    assert_eq!(
+        parse("2 * (3 + (4))", &PrecedenceTable::equal()),
+        Ok(binary(Operator::Mul, 2, number(2), binary(Operator::Add, 7, number(3), number(4))))
+    );
+}
 
-        input = left_over;
-        if input.len() == 0 {
-            break;
-        }
+#[test]
+fn test_parse_errors() {
+    let errors = vec![
+        ("1 +", SyntaxErrorKind::UnexpectedEnd, Span::new(3, 4)),
+        ("", SyntaxErrorKind::UnexpectedEnd, Span::new(0, 1)),
+        ("1 + ) 2", SyntaxErrorKind::ExpectedOperand, Span::new(4, 5)),
+        ("1 2", SyntaxErrorKind::ExpectedOperator, Span::new(2, 3)),
+        ("(1 + 2", SyntaxErrorKind::UnclosedParenthesis, Span::new(0, 1)),
+        ("(1 + 2 3)", SyntaxErrorKind::ExpectedOperator, Span::new(7, 8)),
+        ("1 + 2)", SyntaxErrorKind::ExpectedOperator, Span::new(5, 6)),
+        ("()", SyntaxErrorKind::ExpectedOperand, Span::new(1, 2)),
+    ];
+
+    for (input, kind, span) in errors {
+        let error = parse(input, &PrecedenceTable::equal()).unwrap_err();
+        assert_eq!((error.kind, error.span), (kind, span), "{}", input);
     }
-    itertools::assert_equal(tokens, vec![
-        Token::Operand(1),
-        Token::Operator(Operator::Add),
-        Token::Operand(2),
-        Token::Operator(Operator::Mul),
-        Token::Operand(3),
-        Token::Operator(Operator::Add),
-        Token::Operand(4),
-        Token::Operator(Operator::Mul),
-        Token::Operand(5),
-        Token::Operator(Operator::Add),
-        Token::Operand(6)
-    ]);
+}
+
+#[test]
+fn test_syntax_error_show() {
+    let input = "2 * (3 + 4";
+    let error = parse(input, &PrecedenceTable::equal()).unwrap_err();
+    assert_eq!(error.to_string(), "Column 5: This parenthesis is never closed");
+    assert_eq!(error.show(input), "2 * (3 + 4\n    ^\nColumn 5: This parenthesis is never closed");
+}
 
-    assert_eq!(next_token(" 3 + 4"), (Token::Operand(3), "+ 4"));
+#[test]
+fn test_precedence_table_parse() {
+    let table = PrecedenceTable::parse("# Tightest first\n\nright ^\nprefix -\n  left * / %\nleft + -\n").unwrap();
+    assert_eq!(table, PrecedenceTable::standard());
+    assert_eq!(table.to_string(), "right ^\nprefix -\nleft * / %\nleft + -");
+    assert_eq!(PrecedenceTable::parse(&table.to_string()), Ok(table));
+
+    let errors = vec![
+        ("left + -\nup * /", TableParseError { line: 2, error: TableError::UnknownAssociativity(String::from("up")) }),
+        ("left + &", TableParseError { line: 1, error: TableError::UnknownOperator(String::from("&")) }),
+        ("left +-", TableParseError { line: 1, error: TableError::UnknownOperator(String::from("+-")) }),
+        ("\nright", TableParseError { line: 2, error: TableError::EmptyLevel }),
+        ("left + *\nleft - +", TableParseError { line: 2, error: TableError::DuplicateOperator(Operator::Add) }),
+        ("prefix - *", TableParseError { line: 1, error: TableError::NotPrefixOperator(Operator::Mul) }),
+        ("prefix -\nprefix -", TableParseError { line: 2, error: TableError::DuplicateOperator(Operator::Sub) }),
+    ];
+    for (input, error) in errors {
+        assert_eq!(PrecedenceTable::parse(input), Err(error));
+    }
 
-    assert_eq!(next_token("(2 * 3) + (4 * (5 + 6))"), (
-        Token::ParenthesisedToken(String::from("2 * 3")),
-        "+ (4 * (5 + 6))"
-    ));
-    assert_eq!(next_token("(4 * (5 + 6))"), (
-        Token::ParenthesisedToken(String::from("4 * (5 + 6)")),
-        ""
-    ));
+    assert_eq!(
+        TableError::UnknownAssociativity(String::from("up")).to_string(),
+        "Expected left, right or prefix at the start of the level, got 'up'"
+    );
 }
 
 #[test]
-fn test_parse() {
-    itertools::assert_equal(parse("3 + 4"), vec![
-        Token::Operand(3), Token::Operator(Operator::Add), Token::Operand(4)
-    ]);
+fn test_precedence_presets() {
+    assert_eq!(PrecedenceTable::preset("equal"), Some(PrecedenceTable::equal()));
+    assert_eq!(PrecedenceTable::preset("addition-first"), Some(PrecedenceTable::addition_first()));
+    assert_eq!(PrecedenceTable::preset("standard"), Some(PrecedenceTable::standard()));
+    assert_eq!(PrecedenceTable::preset("unknown"), None);
 
-    itertools::assert_equal(parse("1 + 2 * 3 + 4 * 5 + 6"), vec![
-        Token::Operand(1),
-        Token::Operator(Operator::Add),
-        Token::Operand(2),
-        Token::Operator(Operator::Mul),
-        Token::Operand(3),
-        Token::Operator(Operator::Add),
-        Token::Operand(4),
-        Token::Operator(Operator::Mul),
-        Token::Operand(5),
-        Token::Operator(Operator::Add),
-        Token::Operand(6)
-    ]);
+    let expression = "2 + 3 * 4 ^ 2 - 1";
+    assert_eq!(calculate_with(expression, &PrecedenceTable::equal()), Ok(399));
+    assert_eq!(calculate_with(expression, &PrecedenceTable::addition_first()), Ok(75));
+    assert_eq!(calculate_with(expression, &PrecedenceTable::standard()), Ok(49));
+}
+
+#[test]
+fn test_associativity() {
+    // Exponents are right associative: 2 ^ (3 ^ 2)
+    assert_eq!(calculate_with("2 ^ 3 ^ 2", &PrecedenceTable::standard()), Ok(512));
+    assert_eq!(calculate_with("2 ^ 3 ^ 2", &PrecedenceTable::equal()), Ok(64));
+
+    let right_to_left = PrecedenceTable::parse("right + - * /").unwrap();
+    assert_eq!(calculate_with("10 - 4 - 3", &right_to_left), Ok(9));
+    assert_eq!(calculate_with("10 - 4 - 3", &PrecedenceTable::equal()), Ok(3));
+    assert_eq!(calculate_with("2 * 3 + 4", &right_to_left), Ok(14));
+
+    let addition_tighter = PrecedenceTable::parse("left + -\nright * /").unwrap();
+    assert_eq!(calculate_with("2 * 3 + 4 * 5", &addition_tighter), Ok(70));
+}
+
+#[test]
+fn test_operator_not_in_table() {
+    let table = PrecedenceTable::parse("left + *").unwrap();
+    let error = parse("1 + 2 - 3", &table).unwrap_err();
+    assert_eq!(error.kind, SyntaxErrorKind::OperatorNotInTable(Operator::Sub));
+    assert_eq!(error.span, Span::new(6, 7));
+}
+
+#[test]
+fn test_unary_minus() {
+    assert_eq!(
+        parse("2 * -3", &PrecedenceTable::equal()),
+        Ok(binary(Operator::Mul, 2, number(2), Expression::negate(Span::new(4, 5), number(3))))
+    );
+
+    // Without a prefix level, negation only takes the operand right after it
+    assert_eq!(calculate_with("-2 ^ 2", &PrecedenceTable::equal()), Ok(4));
+    assert_eq!(calculate_with("-2 ^ 2", &PrecedenceTable::standard()), Ok(-4));
+    assert_eq!(calculate_with("-2 * 3 + 1", &PrecedenceTable::standard()), Ok(-5));
+    assert_eq!(calculate_with("-1 + 1", &PrecedenceTable::parse("left + ^\nprefix -").unwrap()), Ok(-2));
+    assert_eq!(calculate_with("--4 - -(1 - 3)", &PrecedenceTable::standard()), Ok(2));
+    assert_eq!(calculate("1 - - 1"), Ok(2));
+
+    let error = parse("1 + * 2", &PrecedenceTable::standard()).unwrap_err();
+    assert_eq!((error.kind, error.span), (SyntaxErrorKind::NotPrefixOperator(Operator::Mul), Span::new(4, 5)));
+    let error = parse("1 - -", &PrecedenceTable::standard()).unwrap_err();
+    assert_eq!((error.kind, error.span), (SyntaxErrorKind::UnexpectedEnd, Span::new(5, 6)));
+}
+
+#[test]
+fn test_operator_apply() {
+    assert_eq!(Operator::Div.apply(-7, 2), Ok(-3));
+    assert_eq!(Operator::Mod.apply(-7, 2), Ok(-1));
+    assert_eq!(Operator::Mod.apply(7, -2), Ok(1));
+    assert_eq!(Operator::Pow.apply(3, 0), Ok(1));
+    assert_eq!(Operator::Pow.apply(-2, 63), Ok(i64::MIN));
+
+    assert_eq!(Operator::Add.apply(i64::MAX, 1), Err(EvaluationErrorKind::Overflow));
+    assert_eq!(Operator::Sub.apply(i64::MIN, 1), Err(EvaluationErrorKind::Overflow));
+    assert_eq!(Operator::Mul.apply(1 << 32, 1 << 31), Err(EvaluationErrorKind::Overflow));
+    assert_eq!(Operator::Div.apply(i64::MIN, -1), Err(EvaluationErrorKind::Overflow));
+    assert_eq!(Operator::Div.apply(1, 0), Err(EvaluationErrorKind::DivisionByZero));
+    assert_eq!(Operator::Mod.apply(1, 0), Err(EvaluationErrorKind::DivisionByZero));
+    assert_eq!(Operator::Pow.apply(2, 63), Err(EvaluationErrorKind::Overflow));
+    assert_eq!(Operator::Pow.apply(2, -1), Err(EvaluationErrorKind::NegativeExponent));
+
+    // Exponents too large for u32 only work out for a few bases
+    assert_eq!(Operator::Pow.apply(1, 1 << 40), Ok(1));
+    assert_eq!(Operator::Pow.apply(-1, (1 << 40) + 1), Ok(-1));
+    assert_eq!(Operator::Pow.apply(0, 1 << 40), Ok(0));
+    assert_eq!(Operator::Pow.apply(2, 1 << 40), Err(EvaluationErrorKind::Overflow));
+}
+
+#[test]
+fn test_evaluation_errors() {
+    let errors = vec![
+        ("7 / (3 - 3)", EvaluationErrorKind::DivisionByZero, Span::new(2, 3)),
+        ("1 + 10 % 0", EvaluationErrorKind::DivisionByZero, Span::new(7, 8)),
+        ("2 ^ (0 - 1)", EvaluationErrorKind::NegativeExponent, Span::new(2, 3)),
+        ("9223372036854775807 + 1", EvaluationErrorKind::Overflow, Span::new(20, 21)),
+        ("-(0 - 9223372036854775807 - 1)", EvaluationErrorKind::Overflow, Span::new(0, 1)),
+    ];
 
-    itertools::assert_equal(parse("1 + (2 * 3) + (4 * (5 + 6))"), vec![
-        Token::Operand(1),
-        Token::Operator(Operator::Add),
-        Token::ParenthesisGroup(vec![
-            Token::Operand(2),
-            Token::Operator(Operator::Mul),
-            Token::Operand(3),
-        ]),
-        Token::Operator(Operator::Add),
-        Token::ParenthesisGroup(vec![
-            Token::Operand(4),
-            Token::Operator(Operator::Mul),
-            Token::ParenthesisGroup(vec![
-                Token::Operand(5),
-                Token::Operator(Operator::Add),
-                Token::Operand(6),
-            ]),
-        ]),
+    for (input, kind, span) in errors {
+        let error = calculate_with(input, &PrecedenceTable::standard());
+        assert_eq!(error, Err(CalculationError::Evaluation(EvaluationError { kind, span })), "{}", input);
+    }
+
+    let input = "1 + 10 % 0";
+    let error = calculate_with(input, &PrecedenceTable::standard()).unwrap_err();
+    assert_eq!(error.show(input), "1 + 10 % 0\n       ^\nColumn 8: Division by zero");
+}
+
+#[test]
+fn test_variables() {
+    itertools::assert_equal(tokenize("x_1=2").unwrap(), vec![
+        token(TokenKind::Identifier(String::from("x_1")), 0, 3),
+        token(TokenKind::Equals, 3, 4),
+        token(TokenKind::Number(2), 4, 5),
     ]);
+
+    let mut variables = HashMap::new();
+    variables.insert(String::from("x"), 7);
+    let expression = parse("x * (x - 2)", &PrecedenceTable::standard()).unwrap();
+    assert_eq!(expression.evaluate_with(&variables), Ok(35));
+
+    let error = parse("1 + y", &PrecedenceTable::standard()).unwrap().evaluate_with(&variables).unwrap_err();
+    assert_eq!(error.kind, EvaluationErrorKind::UnknownVariable(String::from("y")));
+    assert_eq!(error.span, Span::new(4, 5));
+}
+
+#[test]
+fn test_parse_statement() {
+    let table = PrecedenceTable::standard();
+
+    assert_eq!(
+        parse_statement("total = 3 + 4", &table),
+        Ok(Statement::Assignment {
+            name: String::from("total"),
+            expression: binary(Operator::Add, 10, number(3), number(4)),
+        })
+    );
+    assert_eq!(parse_statement("3 + 4", &table), Ok(Statement::Expression(binary(Operator::Add, 2, number(3), number(4)))));
+
+    let errors = vec![
+        ("x = ", SyntaxErrorKind::UnexpectedEnd, Span::new(4, 5)),
+        ("x = y = 1", SyntaxErrorKind::ExpectedOperator, Span::new(6, 7)),
+        ("1 = 2", SyntaxErrorKind::ExpectedOperator, Span::new(2, 3)),
+        ("= 2", SyntaxErrorKind::ExpectedOperand, Span::new(0, 1)),
+    ];
+    for (input, kind, span) in errors {
+        let error = parse_statement(input, &table).unwrap_err();
+        assert_eq!((error.kind, error.span), (kind, span), "{}", input);
+    }
+}
+
+#[test]
+fn test_expression_printing() {
+    let expression = parse("-(1 + 2) * x ^ 2 ^ y", &PrecedenceTable::standard()).unwrap();
+    assert_eq!(expression.to_string(), "((-(1 + 2)) * (x ^ (2 ^ y)))");
+    assert_eq!(expression.to_rpn(), "1 2 + neg x 2 y ^ ^ *");
+
+    let expression = parse("1 + 2 * 3 + 4", &PrecedenceTable::addition_first()).unwrap();
+    assert_eq!(expression.to_string(), "((1 + 2) * (3 + 4))");
+    assert_eq!(expression.to_rpn(), "1 2 + 3 4 + *");
+}
+
+fn output(session: &mut Session, line: &str) -> String {
+    match session.handle(line) {
+        Reply::Output(output) => output,
+        Reply::Quit => panic!("{} quit the session", line),
+    }
+}
+
+#[test]
+fn test_session() {
+    let mut session = Session::new(PrecedenceTable::standard());
+
+    assert_eq!(output(&mut session, "x = 3 + 4"), "x = 7");
+    assert_eq!(output(&mut session, "  x * 2 + 1"), "15");
+    assert_eq!(output(&mut session, "x = x - 10"), "x = -3");
+    assert_eq!(output(&mut session, "y = 2"), "y = 2");
+    assert_eq!(output(&mut session, ":vars"), "x = -3\ny = 2");
+    assert_eq!(output(&mut session, ""), "");
+
+    assert_eq!(output(&mut session, "1 + 2 * 3"), "7");
+    assert_eq!(output(&mut session, ":precedence addition-first"), PrecedenceTable::addition_first().to_string());
+    assert_eq!(output(&mut session, ":precedence"), PrecedenceTable::addition_first().to_string());
+    assert_eq!(output(&mut session, "1 + 2 * 3"), "9");
+    assert_eq!(output(&mut session, ":tree 1 + 2 * 3"), "((1 + 2) * 3)\n1 2 + 3 *");
+
+    assert_eq!(output(&mut session, "z"), "z\n^\nColumn 1: 'z' hasn't been given a value");
+    assert_eq!(output(&mut session, "z = 1 / 0"), "z = 1 / 0\n      ^\nColumn 7: Division by zero");
+    assert_eq!(output(&mut session, ":tree 1 +"), "1 +\n   ^\nColumn 4: Unexpected end of expression");
+    assert_eq!(output(&mut session, ":vars"), "x = -3\ny = 2");
+    assert_eq!(output(&mut session, ":bogus"), "Unknown command :bogus, try :help");
+
+    assert!(matches!(session.handle(":quit"), Reply::Quit));
 }
 
 #[test]
 fn test_calculate() {
     assert_eq!(
-        calculate(&parse("3 + 4")),
-        7
+        calculate("3 + 4"),
+        Ok(7)
+    );
+
+    assert_eq!(
+        calculate("1 + 2 * 3 + 4 * 5 + 6"),
+        Ok(71)
     );
 
     assert_eq!(
-        calculate(&parse("1 + 2 * 3 + 4 * 5 + 6")),
-        71
+        calculate("1 + (2 * 3) + (4 * (5 + 6))"),
+        Ok(51)
     );
 
     assert_eq!(
-        calculate(&parse("1 + (2 * 3) + (4 * (5 + 6))")),
-        51
+        calculate("2 * 3 + (4 * 5)"),
+        Ok(26)
     );
 
     assert_eq!(
-        calculate(&parse("2 * 3 + (4 * 5)")),
-        26
+        calculate("5 + (8 * 3 + 9 + 3 * 4 * 3)"),
+        Ok(437)
     );
 
     assert_eq!(
-        calculate(&parse("5 + (8 * 3 + 9 + 3 * 4 * 3)")),
-        437
+        calculate("5 * 9 * (7 * 3 * 3 + 9 * 3 + (8 + 6 * 4))"),
+        Ok(12240)
     );
 
     assert_eq!(
-        calculate(&parse("5 * 9 * (7 * 3 * 3 + 9 * 3 + (8 + 6 * 4))")),
-        12240
+        calculate("((2 + 4 * 9) * (6 + 9 * 8 + 6) + 6) + 2 + 4 * 2"),
+        Ok(13632)
     );
 
     assert_eq!(
-        calculate(&parse("((2 + 4 * 9) * (6 + 9 * 8 + 6) + 6) + 2 + 4 * 2")),
-        13632
+        calculate("(1+2)*3"),
+        Ok(9)
     );
 }
 
 #[test]
 fn test_calculate2() {
     assert_eq!(
-        calculate2(&parse("3 + 4")),
-        7
+        calculate2("3 + 4"),
+        Ok(7)
+    );
+
+    assert_eq!(
+        calculate2("1 + 2 * 3 + 4 * 5 + 6"),
+        Ok(231)
     );
 
     assert_eq!(
-        calculate2(&parse("1 + 2 * 3 + 4 * 5 + 6")),
-        231
+        calculate2("1 + (2 * 3) + (4 * (5 + 6))"),
+        Ok(51)
     );
 
     assert_eq!(
-        calculate2(&parse("1 + (2 * 3) + (4 * (5 + 6))")),
-        51
+        calculate2("2 * 3 + (4 * 5)"),
+        Ok(46)
     );
 
     assert_eq!(
-        calculate2(&parse("2 * 3 + (4 * 5)")),
-        46
+        calculate2("5 + (8 * 3 + 9 + 3 * 4 * 3)"),
+        Ok(1445)
     );
 
     assert_eq!(
-        calculate2(&parse("5 + (8 * 3 + 9 + 3 * 4 * 3)")),
-        1445
+        calculate2("5 * 9 * (7 * 3 * 3 + 9 * 3 + (8 + 6 * 4))"),
+        Ok(669060)
     );
 
     assert_eq!(
-        calculate2(&parse("5 * 9 * (7 * 3 * 3 + 9 * 3 + (8 + 6 * 4))")),
-        669060
+        calculate2("((2 + 4 * 9) * (6 + 9 * 8 + 6) + 6) + 2 + 4 * 2"),
+        Ok(23340)
     );
 
     assert_eq!(
-        calculate2(&parse("((2 + 4 * 9) * (6 + 9 * 8 + 6) + 6) + 2 + 4 * 2")),
-        23340
+        calculate2("2*3+4"),
+        Ok(14)
     );
-}
\ No newline at end of file
+}